@@ -0,0 +1,207 @@
+use crate::{
+    block::Block,
+    terminal::{paint, terminal},
+    world::World,
+    Inventory, Position,
+};
+
+#[derive(Debug, Clone)]
+pub struct PieceStats {
+    pub block: Block,
+    pub heatmap: Vec<Vec<usize>>, // solutions in which the block covers each cell, by row
+    pub orientations: Vec<usize>, // solutions in which the block has each rotation
+}
+
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub n_solutions: usize,
+    pub pieces: Vec<PieceStats>,
+    pub always_adjacent: Vec<(Block, Block)>,
+    pub forced_cells: Vec<(Position, Block)>, // cells covered by the same block in every solution
+}
+
+pub fn block_cells(world: &World, id: usize) -> Vec<Position> {
+    let mut cells = Vec::new();
+
+    for (y, row) in world.rows().enumerate() {
+        for (x, value) in row.iter().enumerate() {
+            if *value == id {
+                cells.push((x, y));
+            }
+        }
+    }
+
+    cells
+}
+
+/// The rotation of `block` that covers exactly `cells`, which are in row major order.
+pub fn rotation_of(block: &Block, cells: &[Position]) -> Option<usize> {
+    let anchor = *cells.first()?;
+    let mut offsets: Vec<(i32, i32)> = cells[1..]
+        .iter()
+        .map(|c| (c.0 as i32 - anchor.0 as i32, c.1 as i32 - anchor.1 as i32))
+        .collect();
+    offsets.sort();
+
+    block.get_geometries().iter().position(|geometry| {
+        let mut points = geometry.points.clone();
+        points.sort();
+        points == offsets
+    })
+}
+
+fn adjacent(a: &[Position], b: &[Position]) -> bool {
+    a.iter()
+        .any(|p| b.iter().any(|q| p.0.abs_diff(q.0) + p.1.abs_diff(q.1) == 1))
+}
+
+/// Collects where every block ends up over a set of solutions of the same board.
+pub fn analyse(inventory: &Inventory, solutions: &[World]) -> Analysis {
+    let (width, height) = match solutions.first() {
+        Some(world) => (world.width(), world.height()),
+        None => (0, 0),
+    };
+
+    let mut pieces: Vec<PieceStats> = inventory
+        .iter()
+        .map(|block| PieceStats {
+            block: *block,
+            heatmap: vec![vec![0; width]; height],
+            orientations: vec![0; block.get_geometries().len()],
+        })
+        .collect();
+    let mut adjacent_counts = vec![vec![0; inventory.len()]; inventory.len()];
+
+    for world in solutions.iter() {
+        let cells: Vec<Vec<Position>> = inventory
+            .iter()
+            .map(|block| block_cells(world, block.get_block_id()))
+            .collect();
+
+        for (piece, piece_cells) in pieces.iter_mut().zip(cells.iter()) {
+            for (x, y) in piece_cells.iter() {
+                piece.heatmap[*y][*x] += 1;
+            }
+            if let Some(rotation) = rotation_of(&piece.block, piece_cells) {
+                piece.orientations[rotation] += 1;
+            }
+        }
+
+        for i in 0..inventory.len() {
+            for j in i + 1..inventory.len() {
+                if adjacent(&cells[i], &cells[j]) {
+                    adjacent_counts[i][j] += 1;
+                }
+            }
+        }
+    }
+
+    let n_solutions = solutions.len();
+    let mut always_adjacent = Vec::new();
+    for i in 0..inventory.len() {
+        for j in i + 1..inventory.len() {
+            if n_solutions > 0 && adjacent_counts[i][j] == n_solutions {
+                always_adjacent.push((inventory[i], inventory[j]));
+            }
+        }
+    }
+
+    let mut forced_cells = Vec::new();
+    for piece in pieces.iter() {
+        for (y, row) in piece.heatmap.iter().enumerate() {
+            for (x, count) in row.iter().enumerate() {
+                if n_solutions > 0 && *count == n_solutions {
+                    forced_cells.push(((x, y), piece.block));
+                }
+            }
+        }
+    }
+
+    Analysis {
+        n_solutions,
+        pieces,
+        always_adjacent,
+        forced_cells,
+    }
+}
+
+impl Analysis {
+    /// Prints a heatmap per block, brighter cells are covered by the block in more solutions.
+    pub fn draw_heatmaps(&self) {
+        for piece in self.pieces.iter() {
+            println!("{:?}", piece.block);
+
+            for row in piece.heatmap.iter() {
+                for count in row.iter() {
+                    let share = *count as f64 / self.n_solutions.max(1) as f64;
+                    // from white to orange, light enough for the black text
+                    let shade = (share * 255.0) as u8;
+                    print!(
+                        "{}",
+                        paint(
+                            &format!("{:>3}", (share * 100.0).round() as usize),
+                            (255, 255 - shade / 2, 255 - shade),
+                            terminal().colors
+                        )
+                    );
+                }
+                println!();
+            }
+
+            println!("Orientations used: {:?}", piece.orientations);
+        }
+
+        println!("Always adjacent:");
+        for (a, b) in self.always_adjacent.iter() {
+            println!("  {:?} and {:?}", a, b);
+        }
+
+        println!("Forced cells:");
+        for (pos, block) in self.forced_cells.iter() {
+            println!("  {:?} is always covered by {:?}", pos, block);
+        }
+    }
+
+    pub fn heatmap_csv(&self) -> String {
+        let mut csv = "block,x,y,count,share\n".to_string();
+
+        for piece in self.pieces.iter() {
+            for (y, row) in piece.heatmap.iter().enumerate() {
+                for (x, count) in row.iter().enumerate() {
+                    csv.push_str(&format!(
+                        "{:?},{},{},{},{:.4}\n",
+                        piece.block,
+                        x,
+                        y,
+                        count,
+                        *count as f64 / self.n_solutions.max(1) as f64
+                    ));
+                }
+            }
+        }
+
+        csv
+    }
+
+    pub fn orientations_csv(&self) -> String {
+        let mut csv = "block,rotation,count\n".to_string();
+
+        for piece in self.pieces.iter() {
+            for (rotation, count) in piece.orientations.iter().enumerate() {
+                csv.push_str(&format!("{:?},{},{}\n", piece.block, rotation, count));
+            }
+        }
+
+        csv
+    }
+
+    pub fn adjacency_csv(&self) -> String {
+        let mut csv = "block,other_block\n".to_string();
+
+        for (a, b) in self.always_adjacent.iter() {
+            csv.push_str(&format!("{:?},{:?}\n", a, b));
+        }
+
+        csv
+    }
+}
@@ -0,0 +1,122 @@
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    block::Block, board::Board, calculate_parallel, calculate_sync, Inventory, SolveResult,
+};
+
+pub struct ReferencePuzzle {
+    pub name: &'static str,
+    pub board: Board,
+    pub inventory: Inventory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SolverMode {
+    Sync,
+    Parallel,
+}
+
+pub const SOLVER_MODES: [SolverMode; 2] = [SolverMode::Sync, SolverMode::Parallel];
+
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub puzzle: String,
+    pub mode: SolverMode,
+    pub unique_solutions: usize,
+    pub raw_solutions: usize,
+    pub nodes: usize,
+    pub placements_tested: usize,
+    pub wall_time_ms: f64,
+}
+
+/// The puzzles the solver is measured on, from quick to slow.
+pub fn reference_puzzles() -> Vec<ReferencePuzzle> {
+    let pentominoes: Inventory = crate::default_inventory()
+        .into_iter()
+        .filter(|block| block.get_size() == 5)
+        .collect();
+
+    vec![
+        ReferencePuzzle {
+            name: "squares-4x4",
+            board: Board::rectangle(4, 4),
+            inventory: (1..=4).map(Block::Square).collect(),
+        },
+        ReferencePuzzle {
+            name: "pentominoes-6x10",
+            board: Board::rectangle(10, 6),
+            inventory: pentominoes,
+        },
+        ReferencePuzzle {
+            name: "13-piece-8x8",
+            board: Board::rectangle(8, 8),
+            inventory: crate::default_inventory(),
+        },
+    ]
+}
+
+impl SolverMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SolverMode::Sync => "sync",
+            SolverMode::Parallel => "parallel",
+        }
+    }
+}
+
+pub fn run(puzzle: &ReferencePuzzle, mode: SolverMode) -> BenchResult {
+    let start = Instant::now();
+    let result: SolveResult = match mode {
+        SolverMode::Sync => calculate_sync(&puzzle.board, puzzle.inventory.clone()),
+        SolverMode::Parallel => calculate_parallel(&puzzle.board, puzzle.inventory.clone()),
+    };
+    let wall_time = Instant::now().duration_since(start);
+
+    BenchResult {
+        puzzle: puzzle.name.to_string(),
+        mode,
+        unique_solutions: result.classes.len(),
+        raw_solutions: result.raw_solutions,
+        nodes: result.stats.nodes,
+        placements_tested: result.stats.placements_tested,
+        wall_time_ms: wall_time.as_secs_f64() * 1000.0,
+    }
+}
+
+impl BenchResult {
+    pub fn solutions_per_second(&self) -> f64 {
+        self.raw_solutions as f64 / (self.wall_time_ms / 1000.0).max(f64::EPSILON)
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"puzzle\":\"{}\",\"mode\":\"{}\",\"unique_solutions\":{},\"raw_solutions\":{},\"nodes\":{},\"placements_tested\":{},\"wall_time_ms\":{:.3},\"solutions_per_second\":{:.3}}}",
+            self.puzzle,
+            self.mode.name(),
+            self.unique_solutions,
+            self.raw_solutions,
+            self.nodes,
+            self.placements_tested,
+            self.wall_time_ms,
+            self.solutions_per_second(),
+        )
+    }
+}
+
+/// The report for a whole run, tagged with the crate version, the commit it was built from and
+/// when it ran (seconds since 1970) so runs can be compared.
+pub fn report_json(results: &[BenchResult]) -> String {
+    let results: Vec<String> = results.iter().map(|r| r.to_json()).collect();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Clock is before 1970")
+        .as_secs();
+
+    format!(
+        "{{\"version\":\"{}\",\"git_hash\":\"{}\",\"timestamp\":{},\"results\":[{}]}}",
+        env!("CARGO_PKG_VERSION"),
+        env!("GIT_HASH"),
+        timestamp,
+        results.join(",")
+    )
+}
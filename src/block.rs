@@ -41,6 +41,68 @@ impl Block {
         }
     }
 
+    /// Identifies the kind of block independent of its id, stable across inventories.
+    pub fn get_kind_code(&self) -> u8 {
+        match self {
+            Self::Line(_) => 0,
+            Self::Square(_) => 1,
+            Self::Baton(_) => 2,
+            Self::Bridge(_) => 3,
+            Self::Plus(_) => 4,
+            Self::Corner(_) => 5,
+            Self::T(_) => 6,
+            Self::L(_) => 7,
+            Self::DoubleL(_) => 8,
+            Self::Cursor(_) => 9,
+            Self::Stairs(_) => 10,
+            Self::Z(_) => 11,
+            Self::Weird(_) => 12,
+        }
+    }
+
+    /// Name shown to players.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::Line(_) => "Line",
+            Self::Square(_) => "Square",
+            Self::Baton(_) => "Baton",
+            Self::Bridge(_) => "Bridge",
+            Self::Plus(_) => "Plus",
+            Self::Corner(_) => "Corner",
+            Self::T(_) => "T",
+            Self::L(_) => "L",
+            Self::DoubleL(_) => "Double L",
+            Self::Cursor(_) => "Cursor",
+            Self::Stairs(_) => "Stairs",
+            Self::Z(_) => "Z",
+            Self::Weird(_) => "Weird",
+        }
+    }
+
+    /// The usual pentomino letter of the block, O for the square tetromino.
+    pub fn get_letter(&self) -> char {
+        match self {
+            Self::Line(_) => 'I',
+            Self::Square(_) => 'O',
+            Self::Baton(_) => 'Y',
+            Self::Bridge(_) => 'U',
+            Self::Plus(_) => 'X',
+            Self::Corner(_) => 'V',
+            Self::T(_) => 'T',
+            Self::L(_) => 'L',
+            Self::DoubleL(_) => 'Z',
+            Self::Cursor(_) => 'P',
+            Self::Stairs(_) => 'W',
+            Self::Z(_) => 'N',
+            Self::Weird(_) => 'F',
+        }
+    }
+
+    /// Number of cells the block covers.
+    pub fn get_size(&self) -> usize {
+        self.get_geometries()[0].points.len() + 1
+    }
+
     pub fn get_geometries(&self) -> Vec<BlockGeometry> {
         match self {
             Self::Line(_) => vec![
@@ -182,7 +244,7 @@ impl Block {
                     points: vec![(1, 0), (-1, 1), (0, 1), (1, 1)],
                 },
                 BlockGeometry {
-                    points: vec![(1, 0), (-1, 0), (0, 1), (1, 1)],
+                    points: vec![(1, 0), (2, 0), (1, 1), (2, 1)],
                 },
                 BlockGeometry {
                     points: vec![(0, 1), (1, 1), (0, 2), (1, 2)],
@@ -0,0 +1,137 @@
+use crate::{
+    world::{split_topology, wrap_position, Topology, World, HOLE},
+    Position,
+};
+
+/// The shape of the area that has to be filled. Cells outside of the mask are holes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Board {
+    width: usize,
+    height: usize,
+    mask: Vec<bool>, // row major, true when the cell is part of the board
+    topology: Topology,
+}
+
+impl Board {
+    pub fn rectangle(width: usize, height: usize) -> Self {
+        Board {
+            width,
+            height,
+            mask: vec![true; width * height],
+            topology: Topology::Flat,
+        }
+    }
+
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn with_holes(mut self, holes: &[Position]) -> Self {
+        for (x, y) in holes.iter() {
+            assert!(
+                *x < self.width && *y < self.height,
+                "Hole {},{} is outside of the {}x{} board",
+                x,
+                y,
+                self.width,
+                self.height
+            );
+            self.mask[y * self.width + x] = false;
+        }
+        self
+    }
+
+    /// Parses a board drawn with `.` for cells and `#` (or a space) for holes. Letters are also
+    /// cells, so a world written with `World::to_text` can be read back as its board.
+    /// Shorter lines are padded with holes. A first line like `topology torus` joins the edges.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (topology, text) = split_topology(text)?;
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|line| line.trim_end())
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        let height = lines.len();
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        if width == 0 || height == 0 {
+            return Err("Board is empty".to_string());
+        }
+
+        let mut mask = vec![false; width * height];
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                mask[y * width + x] = match c {
+                    '.' => true,
+                    c if c.is_ascii_alphabetic() => true,
+                    '#' | ' ' => false,
+                    _ => return Err(format!("Unexpected character '{}' at ({}, {})", c, x, y)),
+                };
+            }
+        }
+
+        Ok(Board {
+            width,
+            height,
+            mask,
+            topology,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// The cell `pos` ends up on after crossing the joined edges, None when that is off the
+    /// board or a hole.
+    pub fn wrap(&self, pos: (i32, i32)) -> Option<Position> {
+        wrap_position(self.topology, self.width, self.height, pos).filter(|p| self.contains(*p))
+    }
+
+    pub fn contains(&self, pos: Position) -> bool {
+        pos.0 < self.width && pos.1 < self.height && self.mask[pos.1 * self.width + pos.0]
+    }
+
+    pub fn n_cells(&self) -> usize {
+        self.mask.iter().filter(|c| **c).count()
+    }
+
+    pub fn empty_world(&self) -> World {
+        let mut world = World::new(self.width, self.height).with_topology(self.topology);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !self.contains((x, y)) {
+                    world[(x, y)] = HOLE;
+                }
+            }
+        }
+
+        world
+    }
+}
+
+/// Boards the 13 piece set (or a subset of it) can be played on.
+pub fn preset_boards() -> Vec<Board> {
+    vec![
+        Board::rectangle(8, 8),
+        Board::rectangle(8, 8).with_holes(&[(3, 3), (4, 3), (3, 4), (4, 4)]),
+        Board::rectangle(10, 6),
+        Board::rectangle(12, 5),
+        Board::rectangle(15, 4),
+        Board::rectangle(20, 3),
+    ]
+}
@@ -0,0 +1,372 @@
+use std::io::stdin;
+
+use crate::{
+    analytics::{block_cells, rotation_of},
+    block::Block,
+    board::Board,
+    draw::{render_solution, visible_width, RenderMode},
+    encoding::CanonicalSolution,
+    filtering::world_id,
+    terminal::terminal_columns,
+    world::World,
+    Inventory, Position,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Next,
+    Previous,
+    Jump(usize),              // index as shown, starting at 1
+    Search(String),           // code or world id
+    Covers(char, Position),   // block letter and cell it has to cover
+    Orientation(char, usize), // block letter and rotation it has to have
+    ClearFilters,
+    Help,
+    Quit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Covers(Block, Position),
+    Orientation(Block, usize),
+}
+
+pub struct Browser {
+    pub solutions: Vec<World>,
+    pub codes: Vec<String>, // canonical code of each solution
+    pub ids: Vec<String>,   // world id of each solution
+    pub notes: Vec<String>, // shown with each solution, empty when there is nothing to tell
+    pub inventory: Inventory,
+    pub filters: Vec<Filter>,
+    pub current: usize, // index into `visible()`
+}
+
+const HELP: &str = "\
+enter/n  next solution
+p        previous solution
+<number> jump to a solution
+/<id>    search by code or world id
+c <letter> <x>,<y>  only solutions where the block covers the cell
+o <letter> <rotation>  only solutions where the block has that rotation
+x        clear filters
+q        quit";
+
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let letter = |word: Option<&&str>| -> Result<char, String> {
+        word.and_then(|w| w.chars().next())
+            .map(|c| c.to_ascii_uppercase())
+            .ok_or("Missing block letter".to_string())
+    };
+
+    match words.first().copied() {
+        None | Some("n") => Ok(Command::Next),
+        Some("p") => Ok(Command::Previous),
+        Some("x") => Ok(Command::ClearFilters),
+        Some("h") | Some("?") => Ok(Command::Help),
+        Some("q") => Ok(Command::Quit),
+        Some(search) if search.starts_with('/') => Ok(Command::Search(search[1..].to_string())),
+        Some("c") => {
+            let cell: Vec<usize> = words
+                .get(2)
+                .ok_or("Missing cell")?
+                .split(',')
+                .map(|n| {
+                    n.parse()
+                        .map_err(|_| "Cell should look like 3,4".to_string())
+                })
+                .collect::<Result<_, _>>()?;
+            if cell.len() != 2 {
+                return Err("Cell should look like 3,4".to_string());
+            }
+            Ok(Command::Covers(letter(words.get(1))?, (cell[0], cell[1])))
+        }
+        Some("o") => {
+            let rotation = words
+                .get(2)
+                .and_then(|n| n.parse().ok())
+                .ok_or("Rotation should be a number")?;
+            Ok(Command::Orientation(letter(words.get(1))?, rotation))
+        }
+        Some(number) => match number.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Command::Jump(n)),
+            _ => Err(format!("Unknown command {}, h for help", line)),
+        },
+    }
+}
+
+impl Filter {
+    fn matches(&self, world: &World) -> bool {
+        match self {
+            Filter::Covers(block, pos) => {
+                pos.0 < world.width()
+                    && pos.1 < world.height()
+                    && world[*pos] == block.get_block_id()
+            }
+            Filter::Orientation(block, rotation) => {
+                rotation_of(block, &block_cells(world, block.get_block_id())) == Some(*rotation)
+            }
+        }
+    }
+}
+
+impl Browser {
+    pub fn new(board: &Board, solutions: Vec<World>, inventory: Inventory) -> Self {
+        Browser {
+            codes: solutions
+                .iter()
+                .map(|world| {
+                    // solutions too large to encode have no code
+                    CanonicalSolution::new(board, world, &inventory)
+                        .to_base64()
+                        .unwrap_or_default()
+                })
+                .collect(),
+            ids: solutions
+                .iter()
+                .map(|world| world_id(board, world))
+                .collect(),
+            notes: vec![String::new(); solutions.len()],
+            solutions,
+            inventory,
+            filters: Vec::new(),
+            current: 0,
+        }
+    }
+
+    pub fn with_notes(mut self, notes: Vec<String>) -> Self {
+        assert_eq!(
+            notes.len(),
+            self.solutions.len(),
+            "Every solution needs a note"
+        );
+        self.notes = notes;
+        self
+    }
+
+    /// Indices of the solutions that pass every filter.
+    pub fn visible(&self) -> Vec<usize> {
+        (0..self.solutions.len())
+            .filter(|idx| {
+                self.filters
+                    .iter()
+                    .all(|f| f.matches(&self.solutions[*idx]))
+            })
+            .collect()
+    }
+
+    fn block(&self, letter: char) -> Result<Block, String> {
+        self.inventory
+            .iter()
+            .find(|block| block.get_letter() == letter)
+            .copied()
+            .ok_or(format!("No block with letter {}", letter))
+    }
+
+    /// Why a search found nothing, codes of solutions on other boards are told apart.
+    fn not_found(&self, id: &str) -> String {
+        let size = self.solutions.first().map(|s| (s.width(), s.height()));
+
+        match CanonicalSolution::from_base64(id) {
+            Ok(code) if size.is_some_and(|size| size != (code.width, code.height)) => format!(
+                "Code {} is a solution of a {}x{} board",
+                id, code.width, code.height
+            ),
+            _ => format!("No solution with id {}", id),
+        }
+    }
+
+    /// Applies a command, returns false when the browser should close.
+    pub fn apply(&mut self, command: Command) -> Result<bool, String> {
+        let n_visible = self.visible().len();
+
+        match command {
+            Command::Next => self.current = (self.current + 1).min(n_visible.saturating_sub(1)),
+            Command::Previous => self.current = self.current.saturating_sub(1),
+            Command::Jump(n) if n <= n_visible => self.current = n - 1,
+            Command::Jump(_) => return Err(format!("There are only {} solutions", n_visible)),
+            Command::Search(id) => {
+                let found = self
+                    .visible()
+                    .iter()
+                    .position(|idx| self.codes[*idx] == id || self.ids[*idx] == id);
+                match found {
+                    Some(position) => self.current = position,
+                    None => return Err(self.not_found(&id)),
+                }
+            }
+            Command::Covers(letter, pos) => {
+                self.filters.push(Filter::Covers(self.block(letter)?, pos));
+                self.current = 0;
+            }
+            Command::Orientation(letter, rotation) => {
+                self.filters
+                    .push(Filter::Orientation(self.block(letter)?, rotation));
+                self.current = 0;
+            }
+            Command::ClearFilters => {
+                self.filters.clear();
+                self.current = 0;
+            }
+            Command::Help => println!("{}", HELP),
+            Command::Quit => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Draws the current solution, and the ones after it while they fit in `columns`.
+    pub fn draw(&self, mode: RenderMode, columns: usize) {
+        let visible = self.visible();
+        if visible.is_empty() {
+            println!("No solution passes the filters");
+            return;
+        }
+
+        let mut shown: Vec<Vec<String>> = Vec::new();
+        let mut width = 0;
+        for idx in visible.iter().skip(self.current) {
+            let lines = render_solution(&self.solutions[*idx], &self.inventory, mode);
+            let solution_width = lines.iter().map(|l| visible_width(l)).max().unwrap_or(0) + 4;
+            if !shown.is_empty() && width + solution_width > columns {
+                break;
+            }
+            width += solution_width;
+            shown.push(lines);
+        }
+
+        let height = shown.iter().map(|lines| lines.len()).max().unwrap_or(0);
+        for row in 0..height {
+            let line: Vec<String> = shown
+                .iter()
+                .map(|lines| match lines.get(row) {
+                    Some(line) => line.clone(),
+                    None => " ".repeat(visible_width(&lines[0])),
+                })
+                .collect();
+            println!("{}", line.join("    ").trim_end());
+        }
+
+        let last = self.current + shown.len();
+        let note = &self.notes[visible[self.current]];
+        println!(
+            "Solution {}-{} of {}{}, code: {}{}",
+            self.current + 1,
+            last,
+            visible.len(),
+            match self.filters.len() {
+                0 => String::new(),
+                n => format!(" ({} filter(s), {} in total)", n, self.solutions.len()),
+            },
+            self.codes[visible[self.current]],
+            match note.is_empty() {
+                true => String::new(),
+                false => format!(", {}", note),
+            }
+        );
+    }
+}
+
+/// Shows the solutions one screen at a time and reads commands from stdin until it closes. The
+/// note of the first solution on screen is shown with it.
+pub fn browse(
+    board: &Board,
+    solutions: Vec<World>,
+    notes: Vec<String>,
+    inventory: Inventory,
+    mode: RenderMode,
+) {
+    let mut browser = Browser::new(board, solutions, inventory).with_notes(notes);
+    println!("h for help");
+
+    loop {
+        browser.draw(mode, terminal_columns());
+
+        let mut line = String::new();
+        if stdin().read_line(&mut line).unwrap() == 0 {
+            return;
+        }
+
+        let result = parse_command(&line).and_then(|command| browser.apply(command));
+        match result {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(error) => println!("{}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn browser() -> Browser {
+        let inventory: Inventory = vec![Block::Square(1), Block::Line(2)];
+        let solutions = ["AAB\nAAB\n", "BAA\nBAA\n", "AAB\nAAB\n"]
+            .iter()
+            .map(|text| World::parse(text).unwrap())
+            .collect();
+        // not real solutions, the browser only looks at the cells
+        Browser::new(&Board::rectangle(3, 2), solutions, inventory)
+    }
+
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(parse_command("\n"), Ok(Command::Next));
+        assert_eq!(parse_command("p"), Ok(Command::Previous));
+        assert_eq!(parse_command("12"), Ok(Command::Jump(12)));
+        assert_eq!(
+            parse_command("/abc"),
+            Ok(Command::Search("abc".to_string()))
+        );
+        assert_eq!(parse_command("c o 2,1"), Ok(Command::Covers('O', (2, 1))));
+        assert_eq!(parse_command("o I 1"), Ok(Command::Orientation('I', 1)));
+        assert!(parse_command("0").is_err());
+        assert!(parse_command("c O 2").is_err());
+        assert!(parse_command("jump").is_err());
+    }
+
+    #[test]
+    fn navigation_stays_in_range() {
+        let mut browser = browser();
+
+        browser.apply(Command::Previous).unwrap();
+        assert_eq!(browser.current, 0);
+        browser.apply(Command::Jump(3)).unwrap();
+        browser.apply(Command::Next).unwrap();
+        assert_eq!(browser.current, 2);
+        assert!(browser.apply(Command::Jump(4)).is_err());
+        assert_eq!(browser.apply(Command::Quit), Ok(false));
+    }
+
+    #[test]
+    fn filters_and_search() {
+        let mut browser = browser();
+
+        browser.apply(Command::Covers('O', (0, 0))).unwrap();
+        assert_eq!(browser.visible(), vec![0, 2]);
+        browser.apply(Command::Orientation('I', 0)).unwrap();
+        assert!(browser.visible().is_empty());
+        assert!(browser.apply(Command::Covers('Z', (0, 0))).is_err());
+
+        browser.apply(Command::ClearFilters).unwrap();
+        let code = browser.codes[1].clone();
+        browser.apply(Command::Search(code)).unwrap();
+        assert_eq!(browser.current, 0); // mirror images share their code
+
+        let other_board = CanonicalSolution {
+            width: 2,
+            height: 1,
+            kinds: vec![1],
+            cells: vec![1, 1],
+        };
+        assert_eq!(
+            browser.apply(Command::Search(other_board.to_base64().unwrap())),
+            Err(format!(
+                "Code {} is a solution of a 2x1 board",
+                other_board.to_base64().unwrap()
+            ))
+        );
+    }
+}
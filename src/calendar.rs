@@ -0,0 +1,251 @@
+use std::{
+    collections::HashMap,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    board::Board, discovery::add_possibilities, game::Game, world::World, Inventory, Position,
+};
+
+pub const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const DAYS_IN_MONTH: [usize; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+/// A variant of the classic puzzle-a-day layout, not the original one. The original has 43 cells
+/// and is covered by seven pentominoes and a 2x3 rectangle, which is not one of our blocks. This
+/// variant fills up the last row to 47 cells, so nine pentominoes cover everything but a month
+/// and a day, and its solution counts can not be compared with those of the original.
+pub const DEFAULT_LAYOUT: &str = "\
+Jan Feb Mar Apr May Jun #
+Jul Aug Sep Oct Nov Dec #
+1 2 3 4 5 6 7
+8 9 10 11 12 13 14
+15 16 17 18 19 20 21
+22 23 24 25 26 27 28
+29 30 31 . . . .
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateResult {
+    pub month: usize, // starting at 1
+    pub day: usize,
+    pub solutions: usize,
+}
+
+/// A board whose cells can carry a label, like the months and days of a calendar puzzle.
+#[derive(Debug, Clone)]
+pub struct CalendarBoard {
+    pub board: Board,
+    pub labels: HashMap<Position, String>,
+}
+
+impl CalendarBoard {
+    /// Parses a layout of whitespace separated cells: `.` for a cell without label, `#` for a
+    /// hole and anything else is the label of a cell. Shorter rows are padded with holes.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let rows: Vec<Vec<&str>> = text
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<&str>>())
+            .filter(|row| !row.is_empty())
+            .collect();
+
+        let mut grid = String::new();
+        let mut labels = HashMap::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                match *cell {
+                    "." | "#" => grid.push_str(cell),
+                    label => {
+                        if labels
+                            .values()
+                            .any(|l: &String| l.eq_ignore_ascii_case(label))
+                        {
+                            return Err(format!("Label {} is used twice", label));
+                        }
+                        labels.insert((x, y), label.to_string());
+                        grid.push('.');
+                    }
+                }
+            }
+            grid.push('\n');
+        }
+
+        Ok(CalendarBoard {
+            board: Board::parse(&grid)?,
+            labels,
+        })
+    }
+
+    /// The cell with `label`, ignoring case.
+    pub fn find(&self, label: &str) -> Result<Position, String> {
+        self.labels
+            .iter()
+            .find(|(_, l)| l.eq_ignore_ascii_case(label))
+            .map(|(pos, _)| *pos)
+            .ok_or(format!("No cell is labelled {}", label))
+    }
+
+    /// The month and day cells of a date, `month` and `day` start at 1.
+    pub fn date_targets(&self, month: usize, day: usize) -> Result<Vec<Position>, String> {
+        Ok(vec![
+            self.find(MONTHS[month - 1])?,
+            self.find(&day.to_string())?,
+        ])
+    }
+
+    /// The board with the target cells taken out, so the solver leaves them empty.
+    pub fn without(&self, targets: &[Position]) -> Board {
+        self.board.clone().with_holes(targets)
+    }
+}
+
+/// Reads a date like `3-14` or `Mar-14`.
+pub fn parse_date(text: &str) -> Result<(usize, usize), String> {
+    let error = || format!("Date {} should look like 3-14 or Mar-14", text);
+    let (month, day) = text.split_once('-').ok_or_else(error)?;
+
+    let month = match month.parse::<usize>() {
+        Ok(month) => month,
+        Err(_) => {
+            1 + MONTHS
+                .iter()
+                .position(|m| m.eq_ignore_ascii_case(month))
+                .ok_or_else(error)?
+        }
+    };
+    let day: usize = day.parse().map_err(|_| error())?;
+
+    if !(1..=12).contains(&month) || day == 0 || day > DAYS_IN_MONTH[month - 1] {
+        return Err(format!("{} is not a date", text));
+    }
+    Ok((month, day))
+}
+
+/// Today's month and day in UTC.
+pub fn today() -> (usize, usize) {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Clock is before 1970")
+        .as_secs();
+    // days since 1970-01-01 to a civil date, counting years from March so leap days come last
+    let days = (seconds / 86400) as i64 + 719468;
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+
+    (month as usize, day as usize)
+}
+
+/// The solutions that cover everything but the targets. Blocks may be left over when there are
+/// more than needed. The labels tell every cell apart, so turned or mirrored solutions count as
+/// different ones.
+pub fn solve_targets(
+    calendar: &CalendarBoard,
+    targets: &[Position],
+    inventory: Inventory,
+) -> Vec<World> {
+    let board = calendar.without(targets);
+    let mut game = Game::new(&board, inventory);
+    let mut solved_worlds = Vec::new();
+    add_possibilities(&mut game, &mut solved_worlds);
+
+    solved_worlds
+}
+
+/// The number of solutions of every date of a leap year, with a thread per month.
+pub fn solve_all_dates(
+    calendar: &CalendarBoard,
+    inventory: &Inventory,
+) -> Result<Vec<DateResult>, String> {
+    let mut threads = Vec::new();
+
+    for month in 1..=12 {
+        let targets: Vec<Vec<Position>> = (1..=DAYS_IN_MONTH[month - 1])
+            .map(|day| calendar.date_targets(month, day))
+            .collect::<Result<_, _>>()?;
+        let calendar = calendar.clone();
+        let inventory = inventory.clone();
+
+        threads.push(thread::spawn(move || {
+            targets
+                .iter()
+                .enumerate()
+                .map(|(day, targets)| DateResult {
+                    month,
+                    day: day + 1,
+                    solutions: solve_targets(&calendar, targets, inventory.clone()).len(),
+                })
+                .collect::<Vec<_>>()
+        }));
+    }
+
+    Ok(threads
+        .into_iter()
+        .flat_map(|t| t.join().unwrap())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+
+    #[test]
+    fn layouts_are_parsed_with_labels() {
+        let calendar = CalendarBoard::parse(DEFAULT_LAYOUT).unwrap();
+
+        assert_eq!(calendar.board.n_cells(), 47);
+        assert_eq!(calendar.labels.len(), 43);
+        assert_eq!(calendar.find("mar"), Ok((2, 0)));
+        assert_eq!(calendar.date_targets(3, 14), Ok(vec![(2, 0), (6, 3)]));
+        assert!(calendar.find("32").is_err());
+        assert!(CalendarBoard::parse("A . a\n").is_err());
+    }
+
+    #[test]
+    fn dates_are_parsed() {
+        assert_eq!(parse_date("3-14"), Ok((3, 14)));
+        assert_eq!(parse_date("mar-14"), Ok((3, 14)));
+        assert_eq!(parse_date("Feb-29"), Ok((2, 29)));
+        assert!(parse_date("Feb-30").is_err());
+        assert!(parse_date("13-1").is_err());
+        assert!(parse_date("March 14").is_err());
+
+        let (month, day) = today();
+        assert!(parse_date(&format!("{}-{}", month, day)).is_ok());
+    }
+
+    #[test]
+    fn targets_stay_empty() {
+        let calendar = CalendarBoard::parse("a . .\n. . .\n").unwrap();
+        let targets = vec![calendar.find("a").unwrap()];
+        let solutions = solve_targets(
+            &calendar,
+            &targets,
+            vec![Block::Cursor(1), Block::Square(2)],
+        );
+
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].to_text(), "#AA\nAAA\n");
+
+        let calendar = CalendarBoard::parse(DEFAULT_LAYOUT).unwrap();
+        let targets = calendar.date_targets(3, 14).unwrap();
+        let solutions = solve_targets(&calendar, &targets, crate::calendar_inventory());
+        // the holes on the right leave the layout without symmetries, so no solution is a turned
+        // copy of another even before the labels are taken into account
+        assert_eq!(solutions.len(), 73);
+        assert!(solutions
+            .iter()
+            .all(|world| targets.iter().all(|pos| world[*pos] == crate::world::HOLE)));
+    }
+}
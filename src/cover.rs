@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::world::HOLE;
+
+/// Placements by anchor cell, as the kind of piece and the cells it covers. Cells are numbered
+/// so that every placement is listed under its lowest numbered cell.
+pub type CoverPlacements = Vec<Vec<(usize, Vec<usize>)>>;
+
+struct Search<'a> {
+    placements: &'a CoverPlacements,
+    kind_ids: &'a [Vec<usize>], // ids to give the pieces of each kind
+    used: Vec<usize>,           // pieces placed per kind
+    cells: Vec<usize>,
+    solutions: Vec<Vec<usize>>,
+}
+
+impl Search<'_> {
+    fn run(&mut self) {
+        let cursor = match self.cells.iter().position(|v| *v == 0) {
+            Some(c) => c,
+            None => {
+                self.solutions.push(self.cells.clone());
+                return;
+            }
+        };
+
+        let placements = self.placements;
+        for (kind, cells) in placements[cursor].iter() {
+            let kind = *kind;
+            if self.used[kind] == self.kind_ids[kind].len()
+                || cells.iter().any(|c| self.cells[*c] != 0)
+            {
+                continue;
+            }
+
+            let id = self.kind_ids[kind][self.used[kind]];
+            for c in cells.iter() {
+                self.cells[*c] = id;
+            }
+            self.used[kind] += 1;
+
+            self.run();
+
+            self.used[kind] -= 1;
+            for c in cells.iter() {
+                self.cells[*c] = 0;
+            }
+        }
+    }
+}
+
+/// Finds every way to fill the empty (0) cells of `start`. Pieces of the same kind are
+/// interchangeable, they are searched as one kind with a count to avoid finding every
+/// permutation of them.
+pub fn cover(
+    start: Vec<usize>,
+    placements: &CoverPlacements,
+    kind_ids: &[Vec<usize>],
+) -> Vec<Vec<usize>> {
+    let mut search = Search {
+        placements,
+        kind_ids,
+        used: vec![0; kind_ids.len()],
+        cells: start,
+        solutions: Vec::new(),
+    };
+    search.run();
+
+    search.solutions
+}
+
+/// Each cell as the kind of the piece covering it and that piece's order of first appearance.
+/// A region whose shape matches no kind (a chiral piece mirrored into a shape that is not in the
+/// set) makes the form invalid.
+fn relabeled(
+    cells: &[usize],
+    region_kind: &impl Fn(&[usize]) -> Option<usize>,
+) -> Option<Vec<(usize, usize)>> {
+    let mut regions: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (idx, value) in cells.iter().enumerate() {
+        if *value != 0 && *value != HOLE {
+            regions.entry(*value).or_default().push(idx);
+        }
+    }
+
+    let mut labels: HashMap<usize, (usize, usize)> = HashMap::new();
+    let mut form = Vec::with_capacity(cells.len());
+    for value in cells.iter() {
+        if *value == 0 || *value == HOLE {
+            form.push((*value, 0));
+            continue;
+        }
+
+        let label = match labels.get(value) {
+            Some(label) => *label,
+            None => {
+                let label = (region_kind(&regions[value])? + 1, labels.len() + 1);
+                labels.insert(*value, label);
+                label
+            }
+        };
+        form.push(label);
+    }
+
+    Some(form)
+}
+
+/// Indices of the solutions left after removing the ones that are a symmetry of an earlier one.
+/// `symmetries` are permutations of the cells (`perm[i]` is where cell `i` ends up) and
+/// `region_kind` identifies the kind of piece covering a set of cells, so identical pieces are
+/// interchangeable.
+pub fn unique_solutions(
+    solutions: &[Vec<usize>],
+    symmetries: &[Vec<usize>],
+    region_kind: impl Fn(&[usize]) -> Option<usize>,
+) -> Vec<usize> {
+    let mut seen = HashSet::new();
+    let mut unique = Vec::new();
+
+    for (solution_idx, solution) in solutions.iter().enumerate() {
+        let id = symmetries
+            .iter()
+            .filter_map(|perm| {
+                let mut cells = vec![0; solution.len()];
+                for (idx, value) in solution.iter().enumerate() {
+                    cells[perm[idx]] = *value;
+                }
+                relabeled(&cells, &region_kind)
+            })
+            .min()
+            .unwrap();
+
+        if seen.insert(id) {
+            unique.push(solution_idx);
+        }
+    }
+
+    unique
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+
+use crate::{
+    board::Board,
+    discovery::{
+        add_possibilities_with_stats, next_possible_placements, world_filled, SearchStats,
+    },
+    filtering::solution_classes,
+    game::Game,
+    world::World,
+};
+
+#[derive(Debug, Clone)]
+pub struct Difficulty {
+    pub unique_solutions: usize,
+    pub search_tree_size: usize,
+    pub dead_ends: usize,
+    pub forced_moves: usize,
+    pub branching: Vec<f64>, // average number of possible placements per depth
+    pub solution_length: usize, // blocks placed by the search to reach the first solution
+    pub pieces_until_forced: usize, // blocks to place before every further move is forced
+    pub score: f64,
+}
+
+/// Number of blocks that have to be placed along the path to `solution` before every remaining
+/// position on that path has only one possible placement. Returns the path length as well.
+fn pieces_until_forced(game: &Game, solution: &World) -> (usize, usize) {
+    let mut game = game.clone();
+    let mut placed = 0;
+    let mut last_choice = 0;
+
+    while !world_filled(&game.world) {
+        let possible_placements = next_possible_placements(&game);
+        if possible_placements.len() > 1 {
+            last_choice = placed + 1;
+        }
+
+        for placement in possible_placements.into_iter() {
+            let id = placement.block.get_block_id();
+            game.place_block(placement);
+
+            let matches = game
+                .world
+                .rows()
+                .flatten()
+                .zip(solution.rows().flatten())
+                .all(|(value, solution_value)| (*value == id) == (*solution_value == id));
+            if matches {
+                break;
+            }
+            game.pop_block();
+        }
+        placed += 1;
+    }
+
+    (last_choice, placed)
+}
+
+/// Rates a starting position by searching all of its solutions. The forced move analysis
+/// follows the first solution found.
+pub fn rate(board: &Board, game: &Game) -> Difficulty {
+    let mut stats = SearchStats::default();
+    let mut solved_worlds = Vec::new();
+    add_possibilities_with_stats(&mut game.clone(), &mut solved_worlds, &mut stats);
+
+    let (pieces_until_forced, solution_length) = match solved_worlds.first() {
+        Some(solution) => pieces_until_forced(game, solution),
+        None => (0, 0),
+    };
+
+    let score = if solution_length == 0 {
+        0.0
+    } else {
+        (stats.nodes as f64).log2() * pieces_until_forced as f64 / solution_length as f64
+    };
+
+    Difficulty {
+        unique_solutions: solution_classes(board, &solved_worlds).len(),
+        search_tree_size: stats.nodes,
+        dead_ends: stats.dead_ends,
+        forced_moves: stats.forced_moves,
+        branching: stats
+            .depth_nodes
+            .iter()
+            .zip(stats.depth_children.iter())
+            .map(|(nodes, children)| *children as f64 / *nodes as f64)
+            .collect(),
+        solution_length,
+        pieces_until_forced,
+        score,
+    }
+}
+
+impl Difficulty {
+    pub fn to_json(&self) -> String {
+        let branching: Vec<String> = self.branching.iter().map(|b| format!("{:.3}", b)).collect();
+
+        format!(
+            "{{\"unique_solutions\":{},\"search_tree_size\":{},\"dead_ends\":{},\"forced_moves\":{},\"branching\":[{}],\"solution_length\":{},\"pieces_until_forced\":{},\"score\":{:.3}}}",
+            self.unique_solutions,
+            self.search_tree_size,
+            self.dead_ends,
+            self.forced_moves,
+            branching.join(","),
+            self.solution_length,
+            self.pieces_until_forced,
+            self.score,
+        )
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Unique solutions:    {}", self.unique_solutions)?;
+        writeln!(f, "Search tree size:    {}", self.search_tree_size)?;
+        writeln!(f, "Dead ends:           {}", self.dead_ends)?;
+        writeln!(f, "Forced moves:        {}", self.forced_moves)?;
+        writeln!(
+            f,
+            "Pieces until forced: {}/{}",
+            self.pieces_until_forced, self.solution_length
+        )?;
+        writeln!(f, "Branching per depth:")?;
+        for (depth, branching) in self.branching.iter().enumerate() {
+            writeln!(f, "  {:>2}: {:.2}", depth, branching)?;
+        }
+        write!(f, "Score:               {:.2}", self.score)
+    }
+}
@@ -1,10 +1,14 @@
-use crate::{
-    game::Game,
-    validators::{valid_pos, Valid},
-    Placement, Position, World,
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
-pub fn next_possible_placements(game: &Game) -> Vec<Placement> {
+use crate::{game::Game, world::World, Placement, Position};
+
+fn possible_placements(game: &Game, tested: &mut usize) -> Vec<Placement> {
     let cursor = match cursor_position(&game.world) {
         Some(c) => c,
         None => return Vec::new(),
@@ -13,25 +17,14 @@ pub fn next_possible_placements(game: &Game) -> Vec<Placement> {
     let mut placements = Vec::new();
 
     for block in game.inventory.iter() {
-        for (rotation, geometry) in block.get_geometries().iter().enumerate() {
-            let mut possible = true;
-            for point in geometry.points.iter() {
-                let new_point = ((cursor.0 as i32 + point.0), (cursor.1 as i32 + point.1));
-
-                if valid_pos(new_point) != Valid::Valid
-                    || game.world[new_point.1 as usize][new_point.0 as usize] != 0
-                {
-                    possible = false;
-                    break;
-                }
-            }
-
-            if possible {
-                placements.push(Placement {
-                    block: *block,
-                    rotation,
-                    anchor_pos: cursor,
-                })
+        for table_placement in game.table.get(cursor, block) {
+            *tested += 1;
+            if table_placement
+                .cells
+                .iter()
+                .all(|pos| game.world[*pos] == 0)
+            {
+                placements.push(table_placement.placement);
             }
         }
     }
@@ -39,8 +32,20 @@ pub fn next_possible_placements(game: &Game) -> Vec<Placement> {
     placements
 }
 
+pub fn next_possible_placements(game: &Game) -> Vec<Placement> {
+    possible_placements(game, &mut 0)
+}
+
+/// Like `next_possible_placements`, but records the position in `stats`.
+pub fn next_possible_placements_with_stats(game: &Game, stats: &mut SearchStats) -> Vec<Placement> {
+    let placements = possible_placements(game, &mut stats.placements_tested);
+    stats.branch(game.placements.len(), placements.len());
+
+    placements
+}
+
 fn cursor_position(world: &World) -> Option<Position> {
-    for (y, row) in world.iter().enumerate() {
+    for (y, row) in world.rows().enumerate() {
         for (x, value) in row.iter().enumerate() {
             if *value == 0 {
                 return Some((x, y));
@@ -51,24 +56,170 @@ fn cursor_position(world: &World) -> Option<Position> {
     None
 }
 
-fn world_filled(world: &World) -> bool {
+pub fn world_filled(world: &World) -> bool {
     cursor_position(world).is_none()
 }
 
+/// Counters collected while searching, used to measure how hard a position is.
+#[derive(Debug, Clone, Default)]
+pub struct SearchStats {
+    pub nodes: usize,               // positions visited
+    pub dead_ends: usize,           // positions without any possible placement
+    pub forced_moves: usize,        // positions with exactly one possible placement
+    pub placements_tested: usize,   // placements checked for fitting on the board
+    pub depth_nodes: Vec<usize>,    // positions visited per number of placed blocks
+    pub depth_children: Vec<usize>, // possible placements per number of placed blocks
+}
+
+impl SearchStats {
+    fn visit(&mut self, depth: usize) {
+        if self.depth_nodes.len() <= depth {
+            self.depth_nodes.resize(depth + 1, 0);
+            self.depth_children.resize(depth + 1, 0);
+        }
+
+        self.nodes += 1;
+        self.depth_nodes[depth] += 1;
+    }
+
+    pub fn merge(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.dead_ends += other.dead_ends;
+        self.forced_moves += other.forced_moves;
+        self.placements_tested += other.placements_tested;
+
+        if self.depth_nodes.len() < other.depth_nodes.len() {
+            self.depth_nodes.resize(other.depth_nodes.len(), 0);
+            self.depth_children.resize(other.depth_children.len(), 0);
+        }
+        for (depth, nodes) in other.depth_nodes.iter().enumerate() {
+            self.depth_nodes[depth] += nodes;
+            self.depth_children[depth] += other.depth_children[depth];
+        }
+    }
+
+    fn branch(&mut self, depth: usize, n_placements: usize) {
+        self.visit(depth);
+        self.depth_children[depth] += n_placements;
+        match n_placements {
+            0 => self.dead_ends += 1,
+            1 => self.forced_moves += 1,
+            _ => {}
+        }
+    }
+}
+
+/// Stops searches early, it can be cloned into every thread of a search. Without a timeout or
+/// solution limit it only stops when `cancel` is called.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    limit_reached: Arc<AtomicBool>, // like `cancelled`, but not shared by `for_next_search`
+    stopped: Arc<AtomicBool>,       // a search actually returned early
+    solutions: Arc<AtomicUsize>,
+    deadline: Option<Instant>,
+    max_solutions: Option<usize>,
+}
+
+impl CancelToken {
+    pub fn new(timeout: Option<Duration>, max_solutions: Option<usize>) -> Self {
+        CancelToken {
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            max_solutions,
+            ..CancelToken::default()
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// A token for another search with the same deadline and cancel flag, which counts its own
+    /// solutions against the limit and tells on its own whether it stopped early.
+    pub fn for_next_search(&self) -> Self {
+        CancelToken {
+            cancelled: self.cancelled.clone(),
+            deadline: self.deadline,
+            max_solutions: self.max_solutions,
+            ..CancelToken::default()
+        }
+    }
+
+    /// Whether the search has to stop, the clock is only read every 1024 positions.
+    #[allow(clippy::manual_is_multiple_of)] // `is_multiple_of` needs Rust 1.87
+    fn check(&self, nodes: usize) -> bool {
+        if !self.cancelled.load(Ordering::Relaxed) && !self.limit_reached.load(Ordering::Relaxed) {
+            let timed_out = nodes % 1024 == 0 && self.deadline.is_some_and(|d| Instant::now() >= d);
+            if !timed_out {
+                return false;
+            }
+            self.cancel();
+        }
+
+        self.stopped.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Counts a solution, false when it is over the limit and should be dropped. The search only
+    /// stops at the first solution over the limit, so finding exactly the limit is not cut short.
+    fn found_solution(&self) -> bool {
+        let found = self.solutions.fetch_add(1, Ordering::Relaxed) + 1;
+
+        match self.max_solutions {
+            Some(max) if found > max => {
+                self.limit_reached.store(true, Ordering::Relaxed);
+                self.stopped.store(true, Ordering::Relaxed);
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// True when some search gave up before it had seen every position.
+    pub fn stopped_early(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
+
 pub fn add_possibilities(game: &mut Game, solved_worlds: &mut Vec<World>) {
+    add_possibilities_with_stats(game, solved_worlds, &mut SearchStats::default());
+}
+
+pub fn add_possibilities_with_stats(
+    game: &mut Game,
+    solved_worlds: &mut Vec<World>,
+    stats: &mut SearchStats,
+) {
+    add_possibilities_until(game, solved_worlds, stats, &CancelToken::default());
+}
+
+/// Like `add_possibilities_with_stats`, but gives up as soon as `token` is cancelled.
+pub fn add_possibilities_until(
+    game: &mut Game,
+    solved_worlds: &mut Vec<World>,
+    stats: &mut SearchStats,
+    token: &CancelToken,
+) {
+    if token.check(stats.nodes) {
+        return;
+    }
+
     if world_filled(&game.world) {
-        solved_worlds.push(game.world);
+        stats.visit(game.placements.len());
+        if token.found_solution() {
+            solved_worlds.push(game.world.clone());
+        }
         return;
     }
 
-    let possible_placements = next_possible_placements(game);
+    let possible_placements = next_possible_placements_with_stats(game, stats);
     if possible_placements.is_empty() {
         return;
     }
 
     for placement in possible_placements.into_iter() {
         game.place_block(placement);
-        add_possibilities(game, solved_worlds);
+        add_possibilities_until(game, solved_worlds, stats, token);
         game.pop_block();
     }
 }
@@ -0,0 +1,276 @@
+use crate::{
+    board::Board,
+    discovery::{
+        add_possibilities_with_stats, next_possible_placements, world_filled, SearchStats,
+    },
+    filtering::filter_out_duplicates,
+    game::Game,
+    world::{Topology, World},
+    Inventory, Placement,
+};
+
+/// What a worker found in one work unit, written to and read from result files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitResult {
+    pub unit: usize,
+    pub n_units: usize,
+    pub depth: usize,
+    pub blocks: Vec<usize>, // ids of the inventory, to check results belong together
+    pub nodes: usize,
+    pub board: World, // the empty board
+    pub solutions: Vec<World>,
+}
+
+fn visit_prefixes(game: &mut Game, depth: usize, on_prefix: &mut impl FnMut(&[Placement])) {
+    if game.placements.len() == depth || world_filled(&game.world) {
+        on_prefix(&game.placements);
+        return;
+    }
+
+    for placement in next_possible_placements(game) {
+        game.place_block(placement);
+        visit_prefixes(game, depth, on_prefix);
+        game.pop_block();
+    }
+}
+
+/// Splits the search into units: every way to place the first `depth` blocks, in the order the
+/// search would try them. Prefixes that run into a dead end before `depth` are left out, they
+/// can't contain solutions. The numbering only depends on the board, inventory and depth, so
+/// every machine gets the same units.
+pub fn work_units(game: &Game, depth: usize) -> Vec<Vec<Placement>> {
+    let mut units = Vec::new();
+    visit_prefixes(&mut game.clone(), depth, &mut |prefix| {
+        units.push(prefix.to_vec())
+    });
+    units
+}
+
+/// Searches unit `unit` (starting at 0) of the units at `depth`. Only that unit's prefix is kept,
+/// the others are just counted.
+pub fn run_unit(
+    board: &Board,
+    inventory: Inventory,
+    depth: usize,
+    unit: usize,
+) -> Result<UnitResult, String> {
+    let blocks = inventory.iter().map(|block| block.get_block_id()).collect();
+    let mut game = Game::new(board, inventory);
+    let mut n_units = 0;
+    let mut prefix = None;
+    visit_prefixes(&mut game.clone(), depth, &mut |placements| {
+        if n_units == unit {
+            prefix = Some(placements.to_vec());
+        }
+        n_units += 1;
+    });
+    let prefix = prefix.ok_or(format!("Unit {} of {} does not exist", unit, n_units))?;
+
+    for placement in prefix.iter() {
+        game.place_block(*placement);
+    }
+    let mut solutions = Vec::new();
+    let mut stats = SearchStats::default();
+    add_possibilities_with_stats(&mut game, &mut solutions, &mut stats);
+
+    Ok(UnitResult {
+        unit,
+        n_units,
+        depth,
+        blocks,
+        nodes: stats.nodes,
+        board: board.empty_world(),
+        solutions,
+    })
+}
+
+impl UnitResult {
+    /// Header lines, the board and the solutions, separated by empty lines.
+    pub fn to_text(&self) -> String {
+        let blocks: Vec<String> = self.blocks.iter().map(|id| id.to_string()).collect();
+        let mut text = format!(
+            "unit {}/{}\ndepth {}\ntopology {}\nblocks {}\nnodes {}\nsolutions {}\n\n{}",
+            self.unit,
+            self.n_units,
+            self.depth,
+            self.board.topology().name(),
+            blocks.join(","),
+            self.nodes,
+            self.solutions.len(),
+            self.board.to_text()
+        );
+
+        for solution in self.solutions.iter() {
+            text.push('\n');
+            text.push_str(&solution.to_text());
+        }
+
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut sections = text.split("\n\n");
+        let header = sections.next().ok_or("Result file is empty")?;
+
+        let field = |name: &str| -> Result<&str, String> {
+            header
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+                .ok_or(format!("Result file has no {}", name))
+        };
+        let number = |name: &str, value: &str| -> Result<usize, String> {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| format!("{} should be a number", name))
+        };
+
+        let (unit, n_units) = field("unit")?
+            .split_once('/')
+            .ok_or("Unit should look like 3/120")?;
+        let blocks = field("blocks")?
+            .split(',')
+            .filter(|id| !id.is_empty())
+            .map(|id| number("blocks", id))
+            .collect::<Result<Vec<usize>, String>>()?;
+        let topology = Topology::parse(field("topology")?.trim())?;
+        let board = World::parse(sections.next().ok_or("Result file has no board")?)?
+            .with_topology(topology);
+        let solutions = sections
+            .filter(|section| !section.trim().is_empty())
+            .map(|section| Ok(World::parse(section)?.with_topology(topology)))
+            .collect::<Result<Vec<World>, String>>()?;
+
+        let expected = number("solutions", field("solutions")?)?;
+        if solutions.len() != expected {
+            return Err(format!(
+                "Result file should have {} solutions but has {}",
+                expected,
+                solutions.len()
+            ));
+        }
+
+        Ok(UnitResult {
+            unit: number("unit", unit)?,
+            n_units: number("unit", n_units)?,
+            depth: number("depth", field("depth")?)?,
+            blocks,
+            nodes: number("nodes", field("nodes")?)?,
+            board,
+            solutions,
+        })
+    }
+}
+
+/// The board and the unique solutions of all results. They have to come from the same puzzle and
+/// depth, units that are missing are listed in the error.
+pub fn merge(results: &[UnitResult]) -> Result<(Board, Vec<World>), String> {
+    let first = results.first().ok_or("Nothing to merge")?;
+
+    for result in results.iter() {
+        // the boards also have to agree on their topology
+        if result.board != first.board || result.blocks != first.blocks {
+            return Err(format!("Unit {} is for a different puzzle", result.unit));
+        }
+        if result.depth != first.depth || result.n_units != first.n_units {
+            return Err(format!(
+                "Unit {} was split at a different depth",
+                result.unit
+            ));
+        }
+    }
+
+    let missing: Vec<String> = (0..first.n_units)
+        .filter(|unit| !results.iter().any(|r| r.unit == *unit))
+        .map(|unit| unit.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("Units {} are missing", missing.join(",")));
+    }
+
+    let board = Board::parse(&first.board.to_text())?.with_topology(first.board.topology());
+    let solutions: Vec<World> = results
+        .iter()
+        .flat_map(|result| result.solutions.iter().cloned())
+        .collect();
+
+    Ok((board.clone(), filter_out_duplicates(&board, &solutions)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+
+    fn squares() -> Inventory {
+        (1..=4).map(Block::Square).collect()
+    }
+
+    #[test]
+    fn units_cover_the_whole_search() {
+        let board = Board::rectangle(4, 4);
+        let game = Game::new(&board, squares());
+
+        assert_eq!(work_units(&game, 0).len(), 1);
+        assert_eq!(work_units(&game, 1).len(), 4);
+        assert_eq!(work_units(&game, 2).len(), 12);
+
+        for depth in 0..=5 {
+            let n_units = work_units(&game, depth).len();
+            let found: usize = (0..n_units)
+                .map(|unit| {
+                    run_unit(&board, squares(), depth, unit)
+                        .unwrap()
+                        .solutions
+                        .len()
+                })
+                .sum();
+            assert_eq!(found, 24, "depth {}", depth);
+        }
+
+        assert_eq!(
+            run_unit(&board, squares(), 2, 12),
+            Err("Unit 12 of 12 does not exist".to_string())
+        );
+    }
+
+    #[test]
+    fn results_round_trip_and_merge() {
+        let board = Board::rectangle(4, 4);
+        let results: Vec<UnitResult> = (0..12)
+            .map(|unit| run_unit(&board, squares(), 2, unit).unwrap())
+            .map(|result| UnitResult::parse(&result.to_text()).unwrap())
+            .collect();
+        assert_eq!(results[5], run_unit(&board, squares(), 2, 5).unwrap());
+
+        let (merged_board, unique) = merge(&results).unwrap();
+        assert_eq!(merged_board, board);
+        assert_eq!(unique.len(), 3);
+
+        assert_eq!(merge(&results[1..]), Err("Units 0 are missing".to_string()));
+        let mut other_depth = results.clone();
+        other_depth[3] = run_unit(&board, squares(), 1, 3).unwrap();
+        assert!(merge(&other_depth).is_err());
+    }
+
+    #[test]
+    fn wrapped_boards_survive_result_files() {
+        let torus = Board::rectangle(4, 4).with_topology(Topology::Torus);
+        assert_eq!(
+            Board::parse("topology torus\n....\n....\n....\n....\n"),
+            Ok(torus.clone())
+        );
+
+        let n_units = work_units(&Game::new(&torus, squares()), 1).len();
+        let mut results: Vec<UnitResult> = (0..n_units)
+            .map(|unit| run_unit(&torus, squares(), 1, unit).unwrap())
+            .map(|result| UnitResult::parse(&result.to_text()).unwrap())
+            .collect();
+        let (merged_board, unique) = merge(&results).unwrap();
+        assert_eq!(merged_board, torus);
+        assert_eq!(unique.len(), 6);
+
+        results[0] = run_unit(&Board::rectangle(4, 4), squares(), 1, 0).unwrap();
+        assert!(merge(&results).is_err());
+    }
+}
@@ -1,29 +1,248 @@
+use std::collections::HashMap;
+
 use crate::{
+    block::Block,
+    palette::{block_colors, style, Rgb},
+    terminal::{paint, terminal, ColorDepth},
     validators::{valid_pos, Valid},
-    Offset, Position, World, COLORS, WORLD_HEIGHT, WORLD_WIDTH,
+    world::{id_char, World, HOLE},
+    Inventory, Offset, Position,
 };
-use colored::Colorize;
 
 fn border_on_offset(world: &World, pos: Position, same_as: &usize, offset: Offset) -> bool {
     let new_pos = (pos.0 as i32 + offset.0, pos.1 as i32 + offset.1);
 
-    if valid_pos(new_pos) != Valid::Valid {
-        return true;
+    match world.wrap(new_pos) {
+        Some(new_pos) => world[new_pos] != *same_as,
+        None => true,
     }
+}
 
-    world[new_pos.1 as usize][new_pos.0 as usize] != *same_as
+/// Whether the block continues on the other side of an edge the topology joins.
+fn crosses_seam(world: &World, pos: Position, same_as: &usize, offset: Offset) -> bool {
+    let new_pos = (pos.0 as i32 + offset.0, pos.1 as i32 + offset.1);
+
+    valid_pos(world, new_pos) != Valid::Valid && !border_on_offset(world, pos, same_as, offset)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderMode {
+    Color,   // blocks filled with their colour
+    Letters, // every cell shows the letter of its block, for terminals without colours
 }
 
 pub fn draw_world(world: &World) {
-    let mut image_map = [[" "; WORLD_WIDTH * 4]; WORLD_HEIGHT * 3];
-    let mut color_map = [[(0, 0, 0); WORLD_WIDTH]; WORLD_HEIGHT];
+    draw_world_with_colors(world, &block_colors(world, &Inventory::new(), style()));
+}
+
+/// Draws a world with colours picked beforehand, every block of it needs one.
+pub fn draw_world_with_colors(world: &World, colors: &HashMap<usize, Rgb>) {
+    for line in render_colored(world, colors, &HashMap::new()) {
+        println!("{}", line);
+    }
+}
+
+/// Draws a world with the labels of the cells no block covers, up to four characters each.
+pub fn draw_labelled_world(world: &World, labels: &HashMap<Position, String>) {
+    let colors = block_colors(world, &Inventory::new(), style());
+    for line in render_colored(world, &colors, labels) {
+        println!("{}", line);
+    }
+}
+
+/// Draws a solution with a legend of its blocks to the right.
+pub fn draw_solution(world: &World, inventory: &Inventory, mode: RenderMode) {
+    for line in render_solution(world, inventory, mode) {
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Characters a line takes up on screen, without colour escape codes.
+pub fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
 
-    for (y, row) in world.iter().enumerate() {
+    for c in line.chars() {
+        match c {
+            '\x1b' => in_escape = true,
+            'm' if in_escape => in_escape = false,
+            _ if in_escape => {}
+            _ => width += 1,
+        }
+    }
+
+    width
+}
+
+/// A solution with its legend, every line padded to the same width so solutions can be put side
+/// by side.
+pub fn render_solution(world: &World, inventory: &Inventory, mode: RenderMode) -> Vec<String> {
+    let colors = block_colors(world, inventory, style());
+    let (lines, width) = match mode {
+        RenderMode::Color => (
+            render_colored(world, &colors, &HashMap::new()),
+            world.width() * 4,
+        ),
+        RenderMode::Letters => (render_letters(world, inventory), world.width() * 2),
+    };
+    let legend = legend(world, inventory, &colors, mode);
+    let legend_width = legend.iter().map(|l| visible_width(l)).max().unwrap_or(0);
+
+    (0..lines.len().max(legend.len()))
+        .map(|i| {
+            let line = match lines.get(i) {
+                Some(line) => line.clone(),
+                None => " ".repeat(width),
+            };
+            let entry = legend.get(i).cloned().unwrap_or_default();
+            let padding = " ".repeat(legend_width - visible_width(&entry));
+            format!("{}   {}{}", line, entry, padding)
+        })
+        .collect()
+}
+
+/// An SVG image of the world with `cell_size` pixels per cell, blocks are outlined and filled with
+/// the same colours as in the terminal.
+pub fn render_svg(world: &World, inventory: &Inventory, cell_size: usize) -> String {
+    let colors = block_colors(world, inventory, style());
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        world.width() * cell_size,
+        world.height() * cell_size
+    );
+
+    for (y, row) in world.rows().enumerate() {
         for (x, value) in row.iter().enumerate() {
-            if *value == 0 {
+            if *value == HOLE {
                 continue;
             }
-            color_map[y][x] = COLORS[(value - 1) % 7];
+
+            let fill = match colors.get(value) {
+                Some(color) => format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2),
+                None => "#eeeeee".to_string(),
+            };
+            let (left, top) = (x * cell_size, y * cell_size);
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                left, top, cell_size, cell_size, fill
+            ));
+
+            // corners of the side towards each offset, relative to the top left of the cell
+            let sides = [
+                ((0, -1), (0, 0), (1, 0)),
+                ((1, 0), (1, 0), (1, 1)),
+                ((0, 1), (0, 1), (1, 1)),
+                ((-1, 0), (0, 0), (0, 1)),
+            ];
+            for (offset, from, to) in sides {
+                let stroke = if border_on_offset(world, (x, y), value, offset) {
+                    "stroke=\"black\" stroke-width=\"2\""
+                } else if crosses_seam(world, (x, y), value, offset) {
+                    "stroke=\"black\" stroke-width=\"1\" stroke-dasharray=\"4 4\""
+                } else {
+                    continue;
+                };
+                svg.push_str(&format!(
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}/>\n",
+                    left + from.0 * cell_size,
+                    top + from.1 * cell_size,
+                    left + to.0 * cell_size,
+                    top + to.1 * cell_size,
+                    stroke
+                ));
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// The letter of the block's kind, or of its id when there are more blocks of that kind, so
+/// touching blocks of the same kind can be told apart.
+fn block_letter(block: &Block, inventory: &Inventory) -> char {
+    let same_kind = inventory
+        .iter()
+        .filter(|b| b.get_letter() == block.get_letter())
+        .count();
+
+    match same_kind {
+        1 => block.get_letter(),
+        _ => id_char(block.get_block_id()),
+    }
+}
+
+fn render_letters(world: &World, inventory: &Inventory) -> Vec<String> {
+    world
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|value| {
+                    let letter = match inventory.iter().find(|b| b.get_block_id() == *value) {
+                        Some(block) => block_letter(block, inventory),
+                        None if *value == HOLE => ' ',
+                        None => id_char(*value),
+                    };
+                    format!("{} ", letter)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// One line per block that is on the board, in inventory order.
+fn legend(
+    world: &World,
+    inventory: &Inventory,
+    colors: &HashMap<usize, Rgb>,
+    mode: RenderMode,
+) -> Vec<String> {
+    inventory
+        .iter()
+        .filter(|block| world.rows().flatten().any(|v| *v == block.get_block_id()))
+        .map(|block| {
+            let depth = terminal().colors;
+            let swatch = match mode {
+                RenderMode::Color if depth != ColorDepth::None => {
+                    format!("{} ", paint("  ", colors[&block.get_block_id()], depth))
+                }
+                _ => String::new(),
+            };
+            format!(
+                "{}{} {}",
+                swatch,
+                block_letter(block, inventory),
+                block.get_name()
+            )
+        })
+        .collect()
+}
+
+fn render_colored<'a>(
+    world: &World,
+    colors: &HashMap<usize, Rgb>,
+    labels: &'a HashMap<Position, String>,
+) -> Vec<String> {
+    let mut image_map: Vec<Vec<&'a str>> = vec![vec![" "; world.width() * 4]; world.height() * 3];
+    let mut color_map = vec![vec![(0, 0, 0); world.width()]; world.height()];
+    let terminal = terminal();
+    let glyphs = terminal.glyphs.borders();
+
+    for (y, row) in world.rows().enumerate() {
+        for (x, value) in row.iter().enumerate() {
+            if *value == 0 || *value == HOLE {
+                // uncovered labels are black on white, centred in the middle line of the cell
+                if let Some(label) = labels.get(&(x, y)) {
+                    let chars: Vec<(usize, char)> = label.char_indices().take(4).collect();
+                    let start = x * 4 + (4 - chars.len()) / 2;
+                    for (i, (idx, c)) in chars.into_iter().enumerate() {
+                        image_map[y * 3 + 1][start + i] = &label[idx..idx + c.len_utf8()];
+                    }
+                    color_map[y][x] = (255, 255, 255);
+                }
+                continue;
+            }
+            color_map[y][x] = colors[value];
 
             let borders = [
                 border_on_offset(world, (x, y), value, (0, -1)),
@@ -32,61 +251,132 @@ pub fn draw_world(world: &World) {
                 border_on_offset(world, (x, y), value, (-1, 0)),
             ];
 
+            // blocks crossing a joined edge get a dotted line there instead of a border
+            if crosses_seam(world, (x, y), value, (1, 0)) {
+                image_map[y * 3 + 1][x * 4 + 3] = glyphs.seam_vertical;
+            }
+            if crosses_seam(world, (x, y), value, (-1, 0)) {
+                image_map[y * 3 + 1][x * 4] = glyphs.seam_vertical;
+            }
+            if crosses_seam(world, (x, y), value, (0, -1)) {
+                image_map[y * 3][x * 4 + 1] = glyphs.seam_horizontal;
+                image_map[y * 3][x * 4 + 2] = glyphs.seam_horizontal;
+            }
+            if crosses_seam(world, (x, y), value, (0, 1)) {
+                image_map[y * 3 + 2][x * 4 + 1] = glyphs.seam_horizontal;
+                image_map[y * 3 + 2][x * 4 + 2] = glyphs.seam_horizontal;
+            }
+
             if borders[1] {
-                image_map[y * 3][x * 4 + 3] = "🭵";
-                image_map[y * 3 + 1][x * 4 + 3] = "🭵";
-                image_map[y * 3 + 2][x * 4 + 3] = "🭵";
+                image_map[y * 3][x * 4 + 3] = glyphs.right;
+                image_map[y * 3 + 1][x * 4 + 3] = glyphs.right;
+                image_map[y * 3 + 2][x * 4 + 3] = glyphs.right;
             }
 
             if borders[3] {
-                image_map[y * 3][x * 4] = "🭰";
-                image_map[y * 3 + 1][x * 4] = "🭰";
-                image_map[y * 3 + 2][x * 4] = "🭰";
+                image_map[y * 3][x * 4] = glyphs.left;
+                image_map[y * 3 + 1][x * 4] = glyphs.left;
+                image_map[y * 3 + 2][x * 4] = glyphs.left;
             }
 
             if borders[0] {
-                image_map[y * 3][x * 4 + 1] = "🭶";
-                image_map[y * 3][x * 4 + 2] = "🭶";
+                image_map[y * 3][x * 4 + 1] = glyphs.top;
+                image_map[y * 3][x * 4 + 2] = glyphs.top;
                 if borders[3] {
-                    image_map[y * 3][x * 4] = "🭽";
+                    image_map[y * 3][x * 4] = glyphs.top_left;
                 } else {
-                    image_map[y * 3][x * 4] = "🭶";
+                    image_map[y * 3][x * 4] = glyphs.top;
                 }
                 if borders[1] {
-                    image_map[y * 3][x * 4 + 3] = "🭾";
+                    image_map[y * 3][x * 4 + 3] = glyphs.top_right;
                 } else {
-                    image_map[y * 3][x * 4 + 3] = "🭶";
+                    image_map[y * 3][x * 4 + 3] = glyphs.top;
                 }
             }
 
             if borders[2] {
-                image_map[y * 3 + 2][x * 4 + 1] = "🭻";
-                image_map[y * 3 + 2][x * 4 + 2] = "🭻";
+                image_map[y * 3 + 2][x * 4 + 1] = glyphs.bottom;
+                image_map[y * 3 + 2][x * 4 + 2] = glyphs.bottom;
                 if borders[3] {
-                    image_map[y * 3 + 2][x * 4] = "🭼";
+                    image_map[y * 3 + 2][x * 4] = glyphs.bottom_left;
                 } else {
-                    image_map[y * 3 + 2][x * 4] = "🭻";
+                    image_map[y * 3 + 2][x * 4] = glyphs.bottom;
                 }
                 if borders[1] {
-                    image_map[y * 3 + 2][x * 4 + 3] = "🭿";
+                    image_map[y * 3 + 2][x * 4 + 3] = glyphs.bottom_right;
                 } else {
-                    image_map[y * 3 + 2][x * 4 + 3] = "🭻";
+                    image_map[y * 3 + 2][x * 4 + 3] = glyphs.bottom;
                 }
             }
         }
     }
 
-    for (y, row) in image_map.into_iter().enumerate() {
-        for (x, value) in row.into_iter().enumerate() {
-            let color = color_map[y / 3][x / 4];
-            print!(
-                "{}",
-                value
-                    .to_string()
-                    .on_truecolor(color.0, color.1, color.2)
-                    .truecolor(0, 0, 0)
-            );
-        }
-        println!();
+    image_map
+        .into_iter()
+        .enumerate()
+        .map(|(y, row)| {
+            row.into_iter()
+                .enumerate()
+                .map(|(x, value)| paint(value, color_map[y / 3][x / 4], terminal.colors))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Topology;
+
+    #[test]
+    fn uncovered_labels_are_drawn() {
+        let world = World::parse("A.\n").unwrap();
+        let labels = HashMap::from([((0, 0), "x".to_string()), ((1, 0), "Mar".to_string())]);
+        let lines = render_colored(&world, &HashMap::from([(1, (0, 0, 0))]), &labels);
+
+        // colour escapes end in `m`, but have no other letters
+        let letters: String = lines[1]
+            .chars()
+            .filter(|c| c.is_alphabetic() && *c != 'm')
+            .collect();
+        assert_eq!(letters, "Mar");
+    }
+
+    #[test]
+    fn blocks_crossing_the_seam_are_open_there() {
+        let world = World::parse("ABA\n")
+            .unwrap()
+            .with_topology(Topology::Cylinder);
+
+        assert!(crosses_seam(&world, (0, 0), &1, (-1, 0)));
+        assert!(!border_on_offset(&world, (2, 0), &1, (1, 0)));
+        assert!(border_on_offset(&world, (1, 0), &2, (0, 1)));
+        assert!(!crosses_seam(&world, (1, 0), &2, (0, 1)));
+        assert_eq!(
+            render_svg(&world, &Inventory::new(), 10)
+                .matches("stroke-dasharray")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn letters_and_legend_use_block_names() {
+        let world = World::parse("AAB\nAAB\n.#B\n").unwrap();
+        let inventory = vec![Block::Square(1), Block::Line(2), Block::Plus(3)];
+
+        assert_eq!(
+            render_letters(&world, &inventory),
+            vec!["O O I ", "O O I ", ".   I "]
+        );
+        assert_eq!(
+            legend(&world, &inventory, &HashMap::new(), RenderMode::Letters),
+            vec!["O Square", "I Line"]
+        );
+
+        // blocks of the same kind keep their own letters
+        let world = World::parse("AB\nCD\n").unwrap();
+        let inventory: Inventory = (1..=4).map(Block::Square).collect();
+        assert_eq!(render_letters(&world, &inventory), vec!["A B ", "C D "]);
     }
 }
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use crate::{
+    board::Board,
+    symmetry::{apply_transform, transform_group},
+    world::{World, HOLE},
+    Inventory,
+};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A solution described by the kind of block covering each cell instead of by block ids, so it
+/// does not change when the inventory is numbered differently or identical blocks are swapped.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CanonicalSolution {
+    pub width: usize,
+    pub height: usize,
+    pub kinds: Vec<u8>,    // kind code of each block, in order of first appearance
+    pub cells: Vec<usize>, // row major, 0 is empty, n is the n-th block in `kinds`
+}
+
+impl CanonicalSolution {
+    fn relabeled(world: &World, inventory: &Inventory) -> Self {
+        let mut labels: HashMap<usize, usize> = HashMap::new();
+        let mut kinds = Vec::new();
+        let mut cells = Vec::new();
+
+        for row in world.rows() {
+            for value in row.iter() {
+                if *value == 0 || *value == HOLE {
+                    cells.push(*value);
+                    continue;
+                }
+
+                let label = *labels.entry(*value).or_insert_with(|| {
+                    let block = inventory
+                        .iter()
+                        .find(|block| block.get_block_id() == *value)
+                        .expect("World contains a block that is not in the inventory");
+                    kinds.push(block.get_kind_code());
+                    kinds.len()
+                });
+                cells.push(label);
+            }
+        }
+
+        CanonicalSolution {
+            width: world.width(),
+            height: world.height(),
+            kinds,
+            cells,
+        }
+    }
+
+    /// The smallest relabeled form of `world` over all symmetries and shifts of the board.
+    pub fn new(board: &Board, world: &World, inventory: &Inventory) -> Self {
+        transform_group(board)
+            .into_iter()
+            .map(|transform| Self::relabeled(&apply_transform(world, transform), inventory))
+            .min()
+            .unwrap()
+    }
+
+    fn bits_per_cell(&self) -> usize {
+        // codes 0..=n are empty and the blocks, n + 1 is a hole
+        (usize::BITS - (self.kinds.len() + 1).leading_zeros()) as usize
+    }
+
+    /// Layout: width, height, number of blocks, one kind code per block, then the cells packed
+    /// into as few bits as needed. The sizes have to fit in a byte each.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let byte = |value: usize, what: &str| {
+            u8::try_from(value).map_err(|_| format!("{} {} does not fit in a byte", what, value))
+        };
+        let mut bytes = vec![
+            byte(self.width, "Width")?,
+            byte(self.height, "Height")?,
+            byte(self.kinds.len(), "Number of blocks")?,
+        ];
+        bytes.extend_from_slice(&self.kinds);
+
+        let bits = self.bits_per_cell();
+        let hole_code = self.kinds.len() + 1;
+        let mut buffer: u32 = 0;
+        let mut n_buffered = 0;
+
+        for cell in self.cells.iter() {
+            let code = if *cell == HOLE { hole_code } else { *cell };
+            buffer = (buffer << bits) | code as u32;
+            n_buffered += bits;
+
+            while n_buffered >= 8 {
+                n_buffered -= 8;
+                bytes.push((buffer >> n_buffered) as u8);
+            }
+        }
+        if n_buffered > 0 {
+            bytes.push((buffer << (8 - n_buffered)) as u8);
+        }
+
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 3 {
+            return Err("Encoding is too short".to_string());
+        }
+
+        let (width, height, n_blocks) = (bytes[0] as usize, bytes[1] as usize, bytes[2] as usize);
+        if bytes.len() < 3 + n_blocks {
+            return Err("Encoding is missing block kinds".to_string());
+        }
+
+        let mut solution = CanonicalSolution {
+            width,
+            height,
+            kinds: bytes[3..3 + n_blocks].to_vec(),
+            cells: Vec::with_capacity(width * height),
+        };
+
+        let bits = solution.bits_per_cell();
+        let hole_code = n_blocks + 1;
+        let mut packed = bytes[3 + n_blocks..].iter();
+        let mut buffer: u32 = 0;
+        let mut n_buffered = 0;
+
+        while solution.cells.len() < width * height {
+            while n_buffered < bits {
+                let byte = packed.next().ok_or("Encoding is missing cells")?;
+                buffer = (buffer << 8) | *byte as u32;
+                n_buffered += 8;
+            }
+
+            n_buffered -= bits;
+            let code = ((buffer >> n_buffered) & ((1 << bits) - 1)) as usize;
+            solution.cells.push(match code {
+                c if c == hole_code => HOLE,
+                c if c > hole_code => return Err(format!("Invalid cell code {}", c)),
+                c => c,
+            });
+        }
+
+        Ok(solution)
+    }
+
+    pub fn to_base64(&self) -> Result<String, String> {
+        let bytes = self.to_bytes()?;
+        let mut encoded = String::new();
+
+        for chunk in bytes.chunks(3) {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+
+            for i in 0..=chunk.len() {
+                encoded.push(BASE64_ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            }
+        }
+
+        Ok(encoded)
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<Self, String> {
+        let mut bytes = Vec::new();
+        let mut buffer: u32 = 0;
+        let mut n_buffered = 0;
+
+        for c in encoded.trim().bytes() {
+            let value = BASE64_ALPHABET
+                .iter()
+                .position(|b| *b == c)
+                .ok_or(format!("Invalid base64 character '{}'", c as char))?;
+            buffer = (buffer << 6) | value as u32;
+            n_buffered += 6;
+
+            if n_buffered >= 8 {
+                n_buffered -= 8;
+                bytes.push((buffer >> n_buffered) as u8);
+            }
+        }
+
+        Self::from_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+
+    #[test]
+    fn codes_round_trip() {
+        let board = Board::rectangle(4, 3).with_holes(&[(3, 2)]);
+        let world = World::parse("AABB\nAABB\nCCC#\n").unwrap();
+        let inventory = vec![Block::Square(1), Block::Square(2), Block::Line(3)];
+        let solution = CanonicalSolution::new(&board, &world, &inventory);
+
+        let code = solution.to_base64().unwrap();
+        assert_eq!(CanonicalSolution::from_base64(&code), Ok(solution.clone()));
+        assert_eq!(
+            CanonicalSolution::from_bytes(&solution.to_bytes().unwrap()),
+            Ok(solution)
+        );
+    }
+
+    #[test]
+    fn sizes_that_do_not_fit_are_rejected() {
+        let solution = CanonicalSolution {
+            width: 300,
+            height: 1,
+            kinds: vec![],
+            cells: vec![0; 300],
+        };
+        assert_eq!(
+            solution.to_bytes(),
+            Err("Width 300 does not fit in a byte".to_string())
+        );
+
+        let solution = CanonicalSolution {
+            width: 1,
+            height: 1,
+            kinds: vec![0; 256],
+            cells: vec![1],
+        };
+        assert!(solution.to_base64().is_err());
+    }
+}
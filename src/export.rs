@@ -0,0 +1,358 @@
+use crate::{
+    board::Board,
+    placement_table::{PlacementTable, TablePlacement},
+    world::{id_char, World},
+    Inventory, Position,
+};
+
+/// How "at most one of these placements" is written as clauses.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AtMostOne {
+    Pairwise,   // a clause for every pair, no extra variables
+    Sequential, // Sinz's sequential counter, linear in size but adds variables
+}
+
+impl AtMostOne {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "pairwise" => Ok(AtMostOne::Pairwise),
+            "sequential" => Ok(AtMostOne::Sequential),
+            _ => Err(format!(
+                "Unknown encoding {}, use pairwise or sequential",
+                name
+            )),
+        }
+    }
+}
+
+/// The rows of the matrix and the variables of the CNF, in the same order: row `i` is
+/// variable `i + 1`.
+fn rows(board: &Board, inventory: &Inventory) -> Vec<TablePlacement> {
+    PlacementTable::new(board, inventory)
+        .all()
+        .cloned()
+        .collect()
+}
+
+fn board_cells(board: &Board) -> Vec<Position> {
+    (0..board.height())
+        .flat_map(|y| (0..board.width()).map(move |x| (x, y)))
+        .filter(|pos| board.contains(*pos))
+        .collect()
+}
+
+/// Every block has to be placed only when the blocks cover the board exactly, otherwise some
+/// are left over.
+fn blocks_required(board: &Board, inventory: &Inventory) -> bool {
+    inventory
+        .iter()
+        .map(|block| block.get_size())
+        .sum::<usize>()
+        == board.n_cells()
+}
+
+fn cell_name(pos: Position) -> String {
+    format!("r{}c{}", pos.1, pos.0)
+}
+
+/// Exact cover matrix in the format of Knuth's DLX programs: the first line names the columns,
+/// one for every cell and one for every block, every other line is a row naming the block and
+/// the cells it covers. Block columns are secondary (after the `|`) when not every block has to
+/// be used.
+pub fn to_exact_cover(board: &Board, inventory: &Inventory) -> String {
+    let mut columns: Vec<String> = board_cells(board).into_iter().map(cell_name).collect();
+    if !blocks_required(board, inventory) {
+        columns.push("|".to_string());
+    }
+    columns.extend(
+        inventory
+            .iter()
+            .map(|block| id_char(block.get_block_id()).to_string()),
+    );
+
+    let mut text = columns.join(" ");
+    text.push('\n');
+    for row in rows(board, inventory) {
+        text.push(id_char(row.placement.block.get_block_id()));
+        for pos in row.cells.iter() {
+            text.push(' ');
+            text.push_str(&cell_name(*pos));
+        }
+        text.push('\n');
+    }
+
+    text
+}
+
+fn at_most_one(
+    vars: &[usize],
+    encoding: AtMostOne,
+    n_vars: &mut usize,
+    clauses: &mut Vec<Vec<i64>>,
+) {
+    let lit = |var: usize| var as i64;
+
+    match encoding {
+        AtMostOne::Pairwise => {
+            for (i, a) in vars.iter().enumerate() {
+                for b in vars[i + 1..].iter() {
+                    clauses.push(vec![-lit(*a), -lit(*b)]);
+                }
+            }
+        }
+        AtMostOne::Sequential => {
+            if vars.len() < 2 {
+                return;
+            }
+            // s[i] is true when one of vars[0..=i] is true
+            let s: Vec<usize> = (0..vars.len() - 1).map(|i| *n_vars + 1 + i).collect();
+            *n_vars += s.len();
+
+            clauses.push(vec![-lit(vars[0]), lit(s[0])]);
+            for i in 1..vars.len() - 1 {
+                clauses.push(vec![-lit(vars[i]), lit(s[i])]);
+                clauses.push(vec![-lit(s[i - 1]), lit(s[i])]);
+                clauses.push(vec![-lit(vars[i]), -lit(s[i - 1])]);
+            }
+            clauses.push(vec![-lit(vars[vars.len() - 1]), -lit(s[s.len() - 1])]);
+        }
+    }
+}
+
+fn exactly_one(
+    vars: &[usize],
+    encoding: AtMostOne,
+    n_vars: &mut usize,
+    clauses: &mut Vec<Vec<i64>>,
+) {
+    clauses.push(vars.iter().map(|var| *var as i64).collect());
+    at_most_one(vars, encoding, n_vars, clauses);
+}
+
+/// DIMACS CNF with a variable for every placement: every cell is covered exactly once and every
+/// block is used at most once (exactly once when the blocks cover the board exactly). The
+/// comments list what each placement variable is, variables after those are helpers of the
+/// encoding.
+pub fn to_dimacs(board: &Board, inventory: &Inventory, encoding: AtMostOne) -> String {
+    let rows = rows(board, inventory);
+    let mut n_vars = rows.len();
+    let mut clauses: Vec<Vec<i64>> = Vec::new();
+
+    for pos in board_cells(board) {
+        let vars: Vec<usize> = (0..rows.len())
+            .filter(|idx| rows[*idx].cells.contains(&pos))
+            .map(|idx| idx + 1)
+            .collect();
+        exactly_one(&vars, encoding, &mut n_vars, &mut clauses);
+    }
+
+    for block in inventory.iter() {
+        let vars: Vec<usize> = (0..rows.len())
+            .filter(|idx| rows[*idx].placement.block == *block)
+            .map(|idx| idx + 1)
+            .collect();
+        match blocks_required(board, inventory) {
+            true => exactly_one(&vars, encoding, &mut n_vars, &mut clauses),
+            false => at_most_one(&vars, encoding, &mut n_vars, &mut clauses),
+        }
+    }
+
+    let mut text = String::from("c block puzzle, variable: block rotation anchor\n");
+    for (idx, row) in rows.iter().enumerate() {
+        let placement = &row.placement;
+        text.push_str(&format!(
+            "c {} {} {} {},{}\n",
+            idx + 1,
+            id_char(placement.block.get_block_id()),
+            placement.rotation,
+            placement.anchor_pos.0,
+            placement.anchor_pos.1
+        ));
+    }
+
+    text.push_str(&format!("p cnf {} {}\n", n_vars, clauses.len()));
+    for clause in clauses.iter() {
+        let literals: Vec<String> = clause.iter().map(|l| l.to_string()).collect();
+        text.push_str(&format!("{} 0\n", literals.join(" ")));
+    }
+
+    text
+}
+
+/// Puts the selected rows on the board, they have to cover it exactly once.
+fn world_from_rows(
+    board: &Board,
+    rows: &[TablePlacement],
+    selected: &[usize],
+) -> Result<World, String> {
+    let mut world = board.empty_world();
+    let mut used = Vec::new();
+
+    for idx in selected.iter() {
+        let row = rows
+            .get(*idx)
+            .ok_or(format!("There are only {} rows", rows.len()))?;
+        let id = row.placement.block.get_block_id();
+        if used.contains(&id) {
+            return Err(format!("Block {} is used twice", id_char(id)));
+        }
+        used.push(id);
+
+        for pos in row.cells.iter() {
+            if world[*pos] != 0 {
+                return Err(format!("Cell {},{} is covered twice", pos.0, pos.1));
+            }
+            world[*pos] = id;
+        }
+    }
+
+    match board_cells(board).into_iter().find(|pos| world[*pos] == 0) {
+        Some(pos) => Err(format!("Cell {},{} is not covered", pos.0, pos.1)),
+        None => Ok(world),
+    }
+}
+
+/// Reads the rows an exact cover solver picked, as row numbers counted from 0 after the column
+/// line of `to_exact_cover`, separated by whitespace.
+pub fn from_exact_cover(
+    board: &Board,
+    inventory: &Inventory,
+    selection: &str,
+) -> Result<World, String> {
+    let selected = selection
+        .split_whitespace()
+        .map(|n| {
+            n.parse()
+                .map_err(|_| format!("Row {} should be a number", n))
+        })
+        .collect::<Result<Vec<usize>, String>>()?;
+
+    world_from_rows(board, &rows(board, inventory), &selected)
+}
+
+/// Reads the model of a SAT solver for the CNF of `to_dimacs`, either as plain literals or in
+/// the competition format with `s` and `v` lines.
+pub fn from_sat_model(board: &Board, inventory: &Inventory, model: &str) -> Result<World, String> {
+    let rows = rows(board, inventory);
+    let mut selected = Vec::new();
+
+    for line in model.lines() {
+        let line = line.trim();
+        if line.contains("UNSAT") {
+            return Err("The solver found no solution".to_string());
+        }
+        if line.starts_with('c') || line.starts_with('s') || line == "SAT" {
+            continue;
+        }
+
+        for literal in line.trim_start_matches('v').split_whitespace() {
+            let literal: i64 = literal
+                .parse()
+                .map_err(|_| format!("Literal {} should be a number", literal))?;
+            // helper variables of the encoding come after the placements
+            if literal > 0 && literal as usize <= rows.len() {
+                selected.push(literal as usize - 1);
+            }
+        }
+    }
+
+    world_from_rows(board, &rows, &selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+
+    fn satisfied(clauses: &[Vec<i64>], assignment: usize) -> bool {
+        clauses.iter().all(|clause| {
+            clause.iter().any(|l| {
+                let value = assignment >> (l.unsigned_abs() - 1) & 1 == 1;
+                value == (*l > 0)
+            })
+        })
+    }
+
+    fn parse_clauses(cnf: &str) -> (usize, Vec<Vec<i64>>) {
+        let header = cnf.lines().find(|l| l.starts_with("p cnf")).unwrap();
+        let n_vars = header.split(' ').nth(2).unwrap().parse().unwrap();
+        let clauses = cnf
+            .lines()
+            .filter(|l| !l.starts_with('c') && !l.starts_with('p'))
+            .map(|l| {
+                l.split(' ')
+                    .map(|n| n.parse().unwrap())
+                    .filter(|n| *n != 0)
+                    .collect()
+            })
+            .collect();
+        (n_vars, clauses)
+    }
+
+    #[test]
+    fn sequential_encoding_allows_at_most_one() {
+        let mut n_vars = 4;
+        let mut clauses = Vec::new();
+        at_most_one(
+            &[1, 2, 3, 4],
+            AtMostOne::Sequential,
+            &mut n_vars,
+            &mut clauses,
+        );
+        assert_eq!(n_vars, 7);
+
+        for vars in 0..16usize {
+            let satisfiable = (0..8).any(|helpers| satisfied(&clauses, vars | helpers << 4));
+            assert_eq!(satisfiable, vars.count_ones() <= 1, "{:04b}", vars);
+        }
+    }
+
+    #[test]
+    fn models_of_the_cnf_are_the_solutions() {
+        let board = Board::rectangle(4, 2);
+        let inventory = vec![Block::Square(1), Block::Square(2)];
+        let (n_vars, clauses) = parse_clauses(&to_dimacs(&board, &inventory, AtMostOne::Pairwise));
+        assert_eq!(n_vars, 6);
+
+        let models: Vec<usize> = (0..1 << n_vars)
+            .filter(|assignment| satisfied(&clauses, *assignment))
+            .collect();
+        assert_eq!(models.len(), 2);
+
+        let literals: Vec<String> = (0..n_vars)
+            .map(|v| match models[0] >> v & 1 {
+                1 => (v + 1).to_string(),
+                _ => format!("-{}", v + 1),
+            })
+            .collect();
+        let model = format!("s SATISFIABLE\nv {} 0\n", literals.join(" "));
+        let world = from_sat_model(&board, &inventory, &model).unwrap();
+        assert!(world.to_text() == "AABB\nAABB\n" || world.to_text() == "BBAA\nBBAA\n");
+
+        assert!(from_sat_model(&board, &inventory, "s UNSATISFIABLE\n").is_err());
+    }
+
+    #[test]
+    fn exact_cover_rows_round_trip() {
+        let board = Board::rectangle(4, 4);
+        let inventory: Inventory = (1..=4).map(Block::Square).collect();
+        let matrix = to_exact_cover(&board, &inventory);
+        let lines: Vec<&str> = matrix.lines().collect();
+
+        assert_eq!(lines[0].split(' ').count(), 20);
+        assert_eq!(lines.len(), 1 + 4 * 9);
+        assert_eq!(lines[1], "A r0c0 r0c1 r1c1 r1c0");
+
+        // rows go by anchor cell, then by block
+        let world = from_exact_cover(&board, &inventory, "0 9 26 35").unwrap();
+        assert_eq!(world.to_text(), "AABB\nAABB\nCCDD\nCCDD\n");
+        assert_eq!(
+            from_exact_cover(&board, &inventory, "0 1"),
+            Err("Cell 0,0 is covered twice".to_string())
+        );
+        assert_eq!(
+            from_exact_cover(&board, &inventory, "0 4"),
+            Err("Block A is used twice".to_string())
+        );
+    }
+}
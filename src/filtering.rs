@@ -1,57 +1,185 @@
-use std::collections::HashSet;
-
-use crate::{Rotation, World, WORLD_HEIGHT, WORLD_WIDTH};
-
-pub fn world_id(world: &World) -> String {
-    let mut ids = Vec::new();
-
-    for rotation in [
-        Rotation::Zero,
-        Rotation::Ninety,
-        Rotation::OneEighty,
-        Rotation::TwoSeventy,
-    ] {
-        let mut rotation_id = String::new();
-        let mut mirror_id = String::new();
-        let mut diagonal_id = String::new();
-
-        for y in 0..WORLD_HEIGHT {
-            for x in 0..WORLD_WIDTH {
-                let (transformed_x, transformed_y) = match rotation {
-                    Rotation::Zero => (x, y),
-                    Rotation::Ninety => (y, WORLD_HEIGHT - 1 - x),
-                    Rotation::OneEighty => (WORLD_WIDTH - 1 - x, WORLD_HEIGHT - 1 - y),
-                    Rotation::TwoSeventy => (WORLD_WIDTH - 1 - y, x),
-                };
-
-                rotation_id.push_str(&format!("{:0>2}", world[transformed_y][transformed_x]));
-                mirror_id.push_str(&format!(
-                    "{:0>2}",
-                    world[WORLD_HEIGHT - 1 - transformed_y][transformed_x]
-                ));
-                diagonal_id.push_str(&format!("{:0>2}", world[transformed_x][transformed_y]));
+use std::collections::HashMap;
+
+use crate::{
+    board::Board,
+    symmetry::{apply_transform, transform_group, Transform},
+    world::{World, HOLE},
+};
+
+fn raw_world_id(world: &World) -> String {
+    let mut id = String::new();
+
+    for row in world.rows() {
+        for value in row.iter() {
+            if *value == HOLE {
+                id.push_str("##");
+            } else {
+                id.push_str(&format!("{:0>2}", value));
             }
         }
+    }
+
+    id
+}
 
-        ids.push(rotation_id);
-        ids.push(mirror_id);
-        ids.push(diagonal_id);
+/// The transform that maps `world` onto its canonical form, together with the id of that form.
+fn canonical_transform(world: &World, group: &[Transform]) -> (Transform, String) {
+    group
+        .iter()
+        .map(|transform| {
+            (
+                *transform,
+                raw_world_id(&apply_transform(world, *transform)),
+            )
+        })
+        .min_by(|a, b| a.1.cmp(&b.1))
+        .unwrap()
+}
+
+pub fn world_id(board: &Board, world: &World) -> String {
+    canonical_transform(world, &transform_group(board)).1
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassMember {
+    pub world: World,
+    pub transform: Transform, // maps `world` onto the canonical solution of its class
+}
+
+/// A set of raw solutions that are the same solution up to symmetry.
+#[derive(Debug, Clone)]
+pub struct SolutionClass {
+    pub id: String,
+    pub canonical: World,
+    pub members: Vec<ClassMember>,
+    pub stabilizer: Vec<Transform>, // transforms that map the canonical solution onto itself
+}
+
+impl SolutionClass {
+    pub fn is_self_symmetric(&self) -> bool {
+        self.stabilizer.len() > 1
     }
 
-    ids.into_iter().min().unwrap()
+    /// How many raw solutions the class has and the transforms that map them onto the canonical
+    /// one, like `2 raw solution(s), symmetries: Identity, Rotate180`.
+    pub fn summary(&self) -> String {
+        let name = |transform: &Transform| match transform.shift {
+            (0, 0) => format!("{:?}", transform.symmetry),
+            (x, y) => format!("{:?} shifted {},{}", transform.symmetry, x, y),
+        };
+        let symmetries: Vec<String> = self.members.iter().map(|m| name(&m.transform)).collect();
+
+        format!(
+            "{} raw solution(s), symmetries: {}",
+            self.members.len(),
+            symmetries.join(", ")
+        )
+    }
 }
 
-pub fn filter_out_duplicates(solutions: &[World]) -> Vec<World> {
-    let mut solution_ids = HashSet::new();
-    let mut filtered_solutions = Vec::new();
+pub fn solution_classes(board: &Board, solutions: &[World]) -> Vec<SolutionClass> {
+    let group = transform_group(board);
+    let mut class_indices: HashMap<String, usize> = HashMap::new();
+    let mut classes: Vec<SolutionClass> = Vec::new();
 
     for solution in solutions.iter() {
-        let solution_id = world_id(solution);
-        if !solution_ids.contains(&solution_id) {
-            solution_ids.insert(solution_id);
-            filtered_solutions.push(*solution);
+        let (transform, id) = canonical_transform(solution, &group);
+        let member = ClassMember {
+            world: solution.clone(),
+            transform,
+        };
+
+        if let Some(idx) = class_indices.get(&id) {
+            classes[*idx].members.push(member);
+            continue;
         }
+
+        let canonical = apply_transform(solution, transform);
+        let stabilizer = group
+            .iter()
+            .filter(|t| apply_transform(&canonical, **t) == canonical)
+            .copied()
+            .collect();
+
+        class_indices.insert(id.clone(), classes.len());
+        classes.push(SolutionClass {
+            id,
+            canonical,
+            members: vec![member],
+            stabilizer,
+        });
     }
 
-    filtered_solutions
+    classes
+}
+
+pub fn filter_out_duplicates(board: &Board, solutions: &[World]) -> Vec<World> {
+    solution_classes(board, solutions)
+        .into_iter()
+        .map(|class| class.members[0].world.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetry::{symmetry_group, transform_world, SYMMETRIES};
+
+    fn numbered_world(width: usize, height: usize) -> World {
+        let mut world = World::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                world[(x, y)] = 1 + x + y * width;
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn world_id_is_invariant_on_square_boards() {
+        let board = Board::rectangle(8, 8);
+        let world = numbered_world(8, 8);
+        let id = world_id(&board, &world);
+
+        for symmetry in SYMMETRIES {
+            assert_eq!(world_id(&board, &transform_world(&world, symmetry)), id);
+        }
+    }
+
+    #[test]
+    fn world_id_is_invariant_on_rectangles() {
+        let board = Board::rectangle(10, 6);
+        let world = numbered_world(10, 6);
+        let id = world_id(&board, &world);
+
+        for symmetry in symmetry_group(&board) {
+            assert_eq!(world_id(&board, &transform_world(&world, symmetry)), id);
+        }
+        assert_eq!(symmetry_group(&board).len(), 4);
+    }
+
+    #[test]
+    fn distinct_worlds_get_distinct_ids() {
+        let board = Board::rectangle(8, 8);
+        let world = numbered_world(8, 8);
+        let mut other = world.clone();
+        other[(0, 1)] = world[(1, 0)];
+        other[(1, 0)] = world[(0, 1)];
+        other[(7, 7)] = 0;
+
+        assert_ne!(world_id(&board, &world), world_id(&board, &other));
+    }
+
+    #[test]
+    fn classes_summarize_their_members() {
+        let board = Board::rectangle(2, 1);
+        let worlds = [World::parse("AB\n").unwrap(), World::parse("BA\n").unwrap()];
+        let classes = solution_classes(&board, &worlds);
+
+        assert_eq!(classes.len(), 1);
+        assert_eq!(
+            classes[0].summary(),
+            "2 raw solution(s), symmetries: Identity, Rotate180"
+        );
+    }
 }
@@ -1,41 +1,53 @@
-use crate::{block::BlockGeometry, Inventory, Placement, Position, World};
+use std::sync::Arc;
+
+use crate::{
+    board::Board, placement_table::PlacementTable, world::World, Inventory, Placement, Position,
+};
 
 #[derive(Debug, Clone)]
 pub struct Game {
     pub world: World,
     pub placements: Vec<Placement>,
     pub inventory: Inventory,
+    pub table: Arc<PlacementTable>, // shared between clones, so threads don't rebuild it
+}
+
+fn set_values(world: &mut World, cells: &[Position], value: usize) {
+    for pos in cells.iter() {
+        world[*pos] = value;
+    }
 }
 
 impl Game {
-    pub fn new(inventory: Inventory) -> Self {
+    pub fn new(board: &Board, inventory: Inventory) -> Self {
         Game {
-            world: Default::default(),
+            world: board.empty_world(),
             placements: Vec::new(),
+            table: Arc::new(PlacementTable::new(board, &inventory)),
             inventory,
         }
     }
 
-    fn set_values(&mut self, anchor_pos: &Position, geometry: &BlockGeometry, value: usize) {
-        self.world[anchor_pos.1][anchor_pos.0] = value;
+    /// Continues from a world that already has blocks on it, those are taken out of the
+    /// inventory.
+    pub fn from_world(board: &Board, world: World, inventory: Inventory) -> Self {
+        let inventory: Inventory = inventory
+            .into_iter()
+            .filter(|block| !world.rows().flatten().any(|v| *v == block.get_block_id()))
+            .collect();
 
-        for point in geometry.points.iter() {
-            let new_point = (
-                (anchor_pos.0 as i32 + point.0) as usize,
-                (anchor_pos.1 as i32 + point.1) as usize,
-            );
-            self.world[new_point.1][new_point.0] = value;
+        Game {
+            world,
+            placements: Vec::new(),
+            table: Arc::new(PlacementTable::new(board, &inventory)),
+            inventory,
         }
     }
 
     pub fn place_block(&mut self, placement: Placement) {
-        let geometries = placement.block.get_geometries();
-
-        let geometry = geometries.get(placement.rotation).unwrap();
-
-        self.set_values(
-            &placement.anchor_pos,
-            geometry,
+        set_values(
+            &mut self.world,
+            self.table.cells(&placement),
             placement.block.get_block_id(),
         );
 
@@ -55,13 +67,55 @@ impl Game {
     pub fn pop_block(&mut self) -> Option<Placement> {
         let placement = self.placements.pop()?;
 
-        let geometries = placement.block.get_geometries();
-
-        let geometry = geometries.get(placement.rotation).unwrap();
-
-        self.set_values(&placement.anchor_pos, geometry, 0);
+        set_values(&mut self.world, self.table.cells(&placement), 0);
         self.inventory.push(placement.block);
 
         Some(placement)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block::Block, discovery::next_possible_placements};
+
+    fn sorted_ids(inventory: &Inventory) -> Vec<usize> {
+        let mut ids: Vec<usize> = inventory.iter().map(|b| b.get_block_id()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn pop_block_undoes_place_block() {
+        let inventory = vec![
+            Block::Line(1),
+            Block::Plus(10),
+            Block::Square(7),
+            Block::L(13),
+        ];
+        let mut game = Game::new(&Board::rectangle(8, 8), inventory.clone());
+        let start = game.world.clone();
+
+        for first in next_possible_placements(&game) {
+            game.place_block(first);
+            let after_first = game.world.clone();
+
+            for second in next_possible_placements(&game) {
+                game.place_block(second);
+                assert_ne!(game.world, after_first);
+
+                let popped = game.pop_block().unwrap();
+                assert_eq!(popped.anchor_pos, second.anchor_pos);
+                assert_eq!(popped.rotation, second.rotation);
+                assert_eq!(game.world, after_first);
+            }
+
+            game.pop_block();
+            assert_eq!(game.world, start);
+            assert!(game.placements.is_empty());
+            assert_eq!(sorted_ids(&game.inventory), sorted_ids(&inventory));
+        }
+
+        assert!(game.pop_block().is_none());
+    }
+}
@@ -0,0 +1,149 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    board::Board,
+    discovery::{add_possibilities, next_possible_placements, world_filled},
+    filtering::solution_classes,
+    game::Game,
+    world::World,
+    Inventory, Placement,
+};
+
+/// Small xorshift generator, good enough to shuffle pieces around.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        Rng::new(nanos as u64)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+pub struct Challenge {
+    pub inventory: Inventory,
+    pub pre_placed: Vec<Placement>,
+    pub start: World,
+    pub solution: World,
+}
+
+fn subset_with_area(blocks: &Inventory, area: usize, chosen: &mut Inventory) -> bool {
+    if area == 0 {
+        return true;
+    }
+
+    for (i, block) in blocks.iter().enumerate() {
+        if block.get_size() > area {
+            continue;
+        }
+
+        chosen.push(*block);
+        if subset_with_area(&blocks[i + 1..].to_vec(), area - block.get_size(), chosen) {
+            return true;
+        }
+        chosen.pop();
+    }
+
+    false
+}
+
+/// A random subset of `inventory` that covers exactly as many cells as the board has.
+pub fn pick_inventory(board: &Board, inventory: &Inventory, rng: &mut Rng) -> Option<Inventory> {
+    let mut blocks = inventory.clone();
+    rng.shuffle(&mut blocks);
+
+    let mut chosen = Vec::new();
+    if subset_with_area(&blocks, board.n_cells(), &mut chosen) {
+        Some(chosen)
+    } else {
+        None
+    }
+}
+
+fn find_random_solution(game: &mut Game, rng: &mut Rng) -> bool {
+    if world_filled(&game.world) {
+        return true;
+    }
+
+    let mut possible_placements = next_possible_placements(game);
+    rng.shuffle(&mut possible_placements);
+
+    for placement in possible_placements.into_iter() {
+        game.place_block(placement);
+        if find_random_solution(game, rng) {
+            return true;
+        }
+        game.pop_block();
+    }
+
+    false
+}
+
+fn unique_solution_count(board: &Board, game: &Game) -> usize {
+    let mut game = game.clone();
+    let mut solved_worlds = Vec::new();
+    add_possibilities(&mut game, &mut solved_worlds);
+
+    solution_classes(board, &solved_worlds).len()
+}
+
+/// Picks blocks for the board, solves it randomly and then pre-places blocks of that solution,
+/// starting with `min_pre_placed` of them, until the solution is the only one left.
+pub fn generate_challenge(
+    board: &Board,
+    inventory: &Inventory,
+    min_pre_placed: usize,
+    rng: &mut Rng,
+) -> Option<Challenge> {
+    let inventory = pick_inventory(board, inventory, rng)?;
+
+    let mut game = Game::new(board, inventory.clone());
+    if !find_random_solution(&mut game, rng) {
+        return None;
+    }
+    let solution = game.world.clone();
+    let mut placements = game.placements.clone();
+    rng.shuffle(&mut placements);
+
+    let mut challenge_game = Game::new(board, inventory.clone());
+    for (i, placement) in placements.into_iter().enumerate() {
+        challenge_game.place_block(placement);
+        if i + 1 < min_pre_placed {
+            continue;
+        }
+
+        if unique_solution_count(board, &challenge_game) == 1 {
+            return Some(Challenge {
+                inventory,
+                pre_placed: challenge_game.placements.clone(),
+                start: challenge_game.world,
+                solution,
+            });
+        }
+    }
+
+    None
+}
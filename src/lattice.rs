@@ -0,0 +1,570 @@
+use std::collections::HashMap;
+
+use crate::{
+    cover::{cover, unique_solutions, CoverPlacements},
+    draw::draw_world,
+    palette::style,
+    terminal::{paint, paint_text, terminal, ColorDepth, Glyphs, Terminal},
+    world::{id_char, World, HOLE},
+    Inventory, COLORS,
+};
+
+/// A cell as (row, column). Hex cells use axial coordinates with pointy-top hexes, so each row
+/// is shifted half a cell to the right of the one above. Triangle cells alternate between
+/// pointing up (row + column even) and down along a row.
+pub type Cell = (i32, i32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lattice {
+    Square,
+    Hex,
+    Triangle,
+}
+
+/// Triangle cells in three coordinates, one per direction of grid lines. They add up to 2 for
+/// triangles pointing up and 1 for triangles pointing down.
+fn triangle_coordinates(cell: Cell) -> (i32, i32, i32) {
+    let sum = if (cell.0 + cell.1).rem_euclid(2) == 0 {
+        2
+    } else {
+        1
+    };
+    (
+        (cell.1 + sum + cell.0) / 2,
+        -cell.0,
+        (sum + cell.0 - cell.1) / 2,
+    )
+}
+
+fn triangle_cell(coordinates: (i32, i32, i32)) -> Cell {
+    let (a, b, c) = coordinates;
+    (-b, a - c)
+}
+
+impl Lattice {
+    /// Number of rotations in the lattice's point group.
+    pub fn rotations(&self) -> usize {
+        match self {
+            Self::Square => 4,
+            Self::Hex | Self::Triangle => 6,
+        }
+    }
+
+    /// Smallest rotation of the lattice: a quarter turn around the centre of square (0, 0), a
+    /// sixth turn around the centre of hex (0, 0) or a sixth turn around the top corner of
+    /// triangle (0, 0).
+    pub fn rotate(&self, cell: Cell) -> Cell {
+        match self {
+            Self::Square => (cell.1, -cell.0),
+            Self::Hex => (cell.0 + cell.1, -cell.0),
+            Self::Triangle => {
+                let (a, b, c) = triangle_coordinates(cell);
+                triangle_cell((1 - b, 1 - c, 1 - a))
+            }
+        }
+    }
+
+    /// Mirrors a cell over a vertical line through the origin.
+    pub fn reflect(&self, cell: Cell) -> Cell {
+        match self {
+            Self::Square => (cell.0, -cell.1),
+            Self::Hex => (cell.0, -cell.0 - cell.1),
+            Self::Triangle => {
+                let (a, b, c) = triangle_coordinates(cell);
+                triangle_cell((c, b, a))
+            }
+        }
+    }
+
+    pub fn neighbours(&self, cell: Cell) -> Vec<Cell> {
+        let (r, c) = cell;
+        match self {
+            Self::Square => vec![(r - 1, c), (r, c + 1), (r + 1, c), (r, c - 1)],
+            Self::Hex => vec![
+                (r, c + 1),
+                (r - 1, c + 1),
+                (r - 1, c),
+                (r, c - 1),
+                (r + 1, c - 1),
+                (r + 1, c),
+            ],
+            Self::Triangle if (r + c).rem_euclid(2) == 0 => {
+                vec![(r, c - 1), (r, c + 1), (r + 1, c)]
+            }
+            Self::Triangle => vec![(r, c - 1), (r, c + 1), (r - 1, c)],
+        }
+    }
+
+    /// Moves `cells` by the offset from `from` to `to`, when that is a translation of the
+    /// lattice. Triangles can only be moved onto triangles pointing the same way.
+    fn translate(&self, cells: &[Cell], from: Cell, to: Cell) -> Option<Vec<Cell>> {
+        let offset = (to.0 - from.0, to.1 - from.1);
+        if *self == Self::Triangle && (offset.0 + offset.1).rem_euclid(2) != 0 {
+            return None;
+        }
+
+        Some(
+            cells
+                .iter()
+                .map(|c| (c.0 + offset.0, c.1 + offset.1))
+                .collect(),
+        )
+    }
+
+    /// Sorts the cells and moves them so the first one is at the top left of the origin.
+    pub fn normalize(&self, cells: &[Cell]) -> Vec<Cell> {
+        let mut cells = cells.to_vec();
+        cells.sort();
+        let first = cells[0];
+        let origin = match self {
+            Self::Triangle => (0, (first.0 + first.1).rem_euclid(2)),
+            _ => (0, 0),
+        };
+
+        self.translate(&cells, first, origin).unwrap()
+    }
+
+    /// Applies rotations and an optional reflection, as an element of the point group.
+    fn transform(&self, cell: Cell, rotations: usize, reflected: bool) -> Cell {
+        let mut cell = if reflected { self.reflect(cell) } else { cell };
+        for _ in 0..rotations {
+            cell = self.rotate(cell);
+        }
+        cell
+    }
+
+    fn point_group(&self, reflections: bool) -> Vec<(usize, bool)> {
+        let reflected: &[bool] = if reflections {
+            &[false, true]
+        } else {
+            &[false]
+        };
+
+        reflected
+            .iter()
+            .flat_map(|r| (0..self.rotations()).map(move |n| (n, *r)))
+            .collect()
+    }
+
+    /// Every distinct orientation of a shape, normalized.
+    pub fn orientations(&self, cells: &[Cell], reflections: bool) -> Vec<Vec<Cell>> {
+        let mut orientations: Vec<Vec<Cell>> = Vec::new();
+
+        for (rotations, reflected) in self.point_group(reflections) {
+            let transformed: Vec<Cell> = cells
+                .iter()
+                .map(|c| self.transform(*c, rotations, reflected))
+                .collect();
+            let transformed = self.normalize(&transformed);
+            if !orientations.contains(&transformed) {
+                orientations.push(transformed);
+            }
+        }
+
+        orientations
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Polyform {
+    pub id: usize,
+    pub name: String,
+    pub cells: Vec<Cell>,
+}
+
+/// Every free polyform (reflections count as the same piece) of `size` cells: pentominoes on
+/// the square lattice, tetrahexes or pentahexes on the hex lattice, hexiamonds on the triangle
+/// lattice, and so on.
+pub fn polyforms(lattice: Lattice, size: usize) -> Vec<Polyform> {
+    let mut shapes: Vec<Vec<Cell>> = vec![vec![(0, 0)]];
+
+    for _ in 1..size {
+        let mut grown: Vec<Vec<Cell>> = Vec::new();
+
+        for shape in shapes.iter() {
+            for cell in shape.iter() {
+                for neighbour in lattice.neighbours(*cell) {
+                    if shape.contains(&neighbour) {
+                        continue;
+                    }
+
+                    let mut new_shape = shape.clone();
+                    new_shape.push(neighbour);
+                    let canonical = lattice
+                        .orientations(&new_shape, true)
+                        .into_iter()
+                        .min()
+                        .unwrap();
+                    if !grown.contains(&canonical) {
+                        grown.push(canonical);
+                    }
+                }
+            }
+        }
+
+        shapes = grown;
+    }
+
+    shapes
+        .into_iter()
+        .enumerate()
+        .map(|(idx, cells)| Polyform {
+            id: idx + 1,
+            name: format!("{:?} {}", lattice, idx + 1),
+            cells,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct LatticeBoard {
+    pub lattice: Lattice,
+    cells: Vec<Cell>, // sorted, so a placement's first cell is its anchor
+}
+
+impl LatticeBoard {
+    pub fn from_cells(lattice: Lattice, cells: &[Cell]) -> Self {
+        let mut cells = cells.to_vec();
+        cells.sort();
+        cells.dedup();
+
+        LatticeBoard { lattice, cells }
+    }
+
+    /// A parallelogram with sides along the lattice: a rectangle of squares, a rhombus shaped
+    /// area of hexes or `width` by `height` rhombi made of two triangles each.
+    pub fn parallelogram(lattice: Lattice, width: usize, height: usize) -> Self {
+        let mut cells = Vec::new();
+
+        for row in 0..height as i32 {
+            for column in 0..width as i32 {
+                match lattice {
+                    Lattice::Square | Lattice::Hex => cells.push((row, column)),
+                    Lattice::Triangle => {
+                        cells.push(triangle_cell((column, -row, 2 - column + row)));
+                        cells.push(triangle_cell((column, -row, 1 - column + row)));
+                    }
+                }
+            }
+        }
+
+        Self::from_cells(lattice, &cells)
+    }
+
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    fn index(&self, cell: Cell) -> Option<usize> {
+        self.cells.binary_search(&cell).ok()
+    }
+
+    /// The symmetries of the board as permutations of cell indices: `perm[i]` is where cell `i`
+    /// ends up.
+    pub fn symmetries(&self) -> Vec<Vec<usize>> {
+        let mut symmetries = Vec::new();
+
+        for (rotations, reflected) in self.lattice.point_group(true) {
+            let transformed: Vec<Cell> = self
+                .cells
+                .iter()
+                .map(|c| self.lattice.transform(*c, rotations, reflected))
+                .collect();
+            let first = *transformed.iter().min().unwrap();
+
+            let perm: Option<Vec<usize>> = self
+                .lattice
+                .translate(&transformed, first, self.cells[0])
+                .and_then(|moved| moved.iter().map(|c| self.index(*c)).collect());
+            if let Some(perm) = perm {
+                symmetries.push(perm);
+            }
+        }
+
+        symmetries
+    }
+}
+
+/// Pieces with the same shape, they are interchangeable.
+struct Kind {
+    ids: Vec<usize>,
+    orientations: Vec<Vec<Cell>>,
+}
+
+fn group_kinds(lattice: Lattice, pieces: &[Polyform], reflections: bool) -> Vec<Kind> {
+    let mut kinds: Vec<Kind> = Vec::new();
+
+    for piece in pieces.iter() {
+        let orientations = lattice.orientations(&piece.cells, reflections);
+        match kinds
+            .iter_mut()
+            .find(|kind| kind.orientations.contains(&orientations[0]))
+        {
+            Some(kind) => kind.ids.push(piece.id),
+            None => kinds.push(Kind {
+                ids: vec![piece.id],
+                orientations,
+            }),
+        }
+    }
+
+    kinds
+}
+
+/// Finds every way to fill the board with the pieces, as the id covering each board cell. With
+/// `reflections` pieces may be flipped over.
+pub fn solve_lattice(
+    board: &LatticeBoard,
+    pieces: &[Polyform],
+    reflections: bool,
+) -> Vec<Vec<usize>> {
+    let kinds = group_kinds(board.lattice, pieces, reflections);
+    let mut placements: CoverPlacements = vec![Vec::new(); board.cells.len()];
+
+    for (anchor, anchor_placements) in placements.iter_mut().enumerate() {
+        for (kind_idx, kind) in kinds.iter().enumerate() {
+            for orientation in kind.orientations.iter() {
+                let cells: Option<Vec<usize>> = board
+                    .lattice
+                    .translate(orientation, orientation[0], board.cells[anchor])
+                    .and_then(|moved| moved.iter().map(|c| board.index(*c)).collect());
+                if let Some(cells) = cells {
+                    anchor_placements.push((kind_idx, cells));
+                }
+            }
+        }
+    }
+
+    let kind_ids: Vec<Vec<usize>> = kinds.into_iter().map(|kind| kind.ids).collect();
+    cover(vec![0; board.cells.len()], &placements, &kind_ids)
+}
+
+/// Removes solutions that are a rotation or reflection of another one.
+pub fn filter_out_lattice_duplicates(
+    board: &LatticeBoard,
+    pieces: &[Polyform],
+    solutions: &[Vec<usize>],
+    reflections: bool,
+) -> Vec<Vec<usize>> {
+    let mut shapes = HashMap::new();
+    for (kind_idx, kind) in group_kinds(board.lattice, pieces, reflections)
+        .iter()
+        .enumerate()
+    {
+        for orientation in kind.orientations.iter() {
+            shapes.insert(orientation.clone(), kind_idx);
+        }
+    }
+
+    let region_kind = |cells: &[usize]| {
+        let region: Vec<Cell> = cells.iter().map(|idx| board.cells[*idx]).collect();
+        shapes.get(&board.lattice.normalize(&region)).copied()
+    };
+
+    unique_solutions(solutions, &board.symmetries(), region_kind)
+        .into_iter()
+        .map(|idx| solutions[idx].clone())
+        .collect()
+}
+
+/// The blocks of the default inventory with the shapes of `pieces`, when every piece is a
+/// different one of them. Square polyforms like that can be solved by the block solver, which is
+/// much faster than the general cover search.
+pub fn square_blocks(pieces: &[Polyform]) -> Option<Inventory> {
+    let canonical = |cells: &[Cell]| {
+        Lattice::Square
+            .orientations(cells, true)
+            .into_iter()
+            .min()
+            .unwrap()
+    };
+    let blocks = crate::default_inventory();
+    let mut inventory = Vec::new();
+
+    for piece in pieces.iter() {
+        let shape = canonical(&piece.cells);
+        let block = blocks.iter().find(|block| {
+            // block offsets are (x, y), cells (row, column)
+            let mut cells = vec![(0, 0)];
+            cells.extend(
+                block.get_geometries()[0]
+                    .points
+                    .iter()
+                    .map(|(x, y)| (*y, *x)),
+            );
+            canonical(&cells) == shape
+        })?;
+        if inventory.contains(block) {
+            return None;
+        }
+        inventory.push(*block);
+    }
+
+    Some(inventory)
+}
+
+/// The values of the board cells in a world of the block solver, which has a column for every
+/// `x` and a row for every `y`.
+pub fn square_values(board: &LatticeBoard, world: &World) -> Vec<usize> {
+    board
+        .cells
+        .iter()
+        .map(|(row, column)| world[(*column as usize, *row as usize)])
+        .collect()
+}
+
+/// Draws squares like `draw_world`, hexes as two character wide blocks with each row offset by
+/// half a hex and triangles as coloured arrow heads.
+pub fn draw_lattice_world(board: &LatticeBoard, values: &[usize]) {
+    if board.lattice == Lattice::Square {
+        let min_row = board.cells.iter().map(|c| c.0).min().unwrap();
+        let max_row = board.cells.iter().map(|c| c.0).max().unwrap();
+        let min_column = board.cells.iter().map(|c| c.1).min().unwrap();
+        let max_column = board.cells.iter().map(|c| c.1).max().unwrap();
+        let mut world = World::new(
+            (max_column - min_column + 1) as usize,
+            (max_row - min_row + 1) as usize,
+        );
+        for y in 0..world.height() {
+            for x in 0..world.width() {
+                world[(x, y)] = HOLE;
+            }
+        }
+        for (cell, value) in board.cells.iter().zip(values.iter()) {
+            world[((cell.1 - min_column) as usize, (cell.0 - min_row) as usize)] = *value;
+        }
+        draw_world(&world);
+        return;
+    }
+
+    for line in render_lattice_world(board, values, terminal()) {
+        println!("{}", line);
+    }
+}
+
+/// Hex and triangle boards as text. Without colours, or with ASCII glyphs, triangles are drawn as
+/// the letter of their piece so touching pieces can be told apart.
+fn render_lattice_world(board: &LatticeBoard, values: &[usize], terminal: Terminal) -> Vec<String> {
+    let min_row = board.cells.iter().map(|c| c.0).min().unwrap();
+    let max_row = board.cells.iter().map(|c| c.0).max().unwrap();
+    let value_at: HashMap<Cell, usize> = board
+        .cells
+        .iter()
+        .copied()
+        .zip(values.iter().copied())
+        .collect();
+    let arrows = terminal.glyphs != Glyphs::Ascii && terminal.colors != ColorDepth::None;
+    let palette = style().palette.clone().unwrap_or(COLORS.to_vec());
+
+    // horizontal position of a cell in characters
+    let screen_column = |cell: &Cell| match board.lattice {
+        Lattice::Square | Lattice::Triangle => cell.1,
+        Lattice::Hex => 2 * cell.1 + cell.0,
+    };
+    let min_column = board.cells.iter().map(screen_column).min().unwrap();
+    let max_column = board.cells.iter().map(screen_column).max().unwrap();
+    let mut lines = Vec::new();
+
+    for row in min_row..=max_row {
+        let mut line = String::new();
+        let mut column = min_column;
+
+        while column <= max_column + 1 {
+            let cell = board
+                .cells
+                .iter()
+                .find(|c| c.0 == row && screen_column(c) == column);
+            let value = cell.and_then(|c| value_at.get(c)).copied();
+
+            match (board.lattice, cell, value) {
+                (_, Some(_), Some(v)) if v != 0 && v != HOLE => {
+                    let color = palette[(v - 1) % palette.len()];
+                    let text = match board.lattice {
+                        Lattice::Hex => format!("{} ", id_char(v)),
+                        _ if !arrows => id_char(v).to_string(),
+                        _ if (row + column).rem_euclid(2) == 0 => "▲".to_string(),
+                        _ => "▼".to_string(),
+                    };
+                    let text = match (board.lattice, arrows) {
+                        (Lattice::Triangle, true) => paint_text(&text, color, terminal.colors),
+                        _ => paint(&text, color, terminal.colors),
+                    };
+                    line.push_str(&text);
+                }
+                (Lattice::Hex, Some(_), _) => line.push_str(". "),
+                (_, Some(_), _) if terminal.glyphs == Glyphs::Ascii => line.push('.'),
+                (_, Some(_), _) => line.push('·'),
+                _ => {
+                    line.push(' ');
+                    column += 1;
+                    continue;
+                }
+            }
+
+            column += if board.lattice == Lattice::Hex { 2 } else { 1 };
+        }
+
+        lines.push(line.trim_end().to_string());
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block::Block,
+        symmetry::{transform_world, Symmetry},
+    };
+
+    #[test]
+    fn pentominoes_are_blocks() {
+        let inventory = square_blocks(&polyforms(Lattice::Square, 5)).unwrap();
+        assert_eq!(inventory.len(), 12);
+        assert!(inventory.contains(&Block::Weird(4)));
+
+        // only the square tetromino is a block
+        assert!(square_blocks(&polyforms(Lattice::Square, 4)).is_none());
+
+        let board = LatticeBoard::parallelogram(Lattice::Square, 3, 2);
+        let world = World::parse("ABC\nDEF\n").unwrap();
+        assert_eq!(square_values(&board, &world), vec![1, 2, 3, 4, 5, 6]);
+
+        // a wide board solved on its side and turned back
+        let turned = transform_world(&World::parse("AD\nBE\nCF\n").unwrap(), Symmetry::Diagonal);
+        assert_eq!(square_values(&board, &turned), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn known_polyform_counts() {
+        assert_eq!(polyforms(Lattice::Square, 5).len(), 12);
+        assert_eq!(polyforms(Lattice::Hex, 4).len(), 7);
+        assert_eq!(polyforms(Lattice::Triangle, 6).len(), 12);
+    }
+
+    #[test]
+    fn hexiamonds_tile_the_rhombus() {
+        let board = LatticeBoard::parallelogram(Lattice::Triangle, 6, 6);
+        let pieces = polyforms(Lattice::Triangle, 6);
+
+        let solutions = solve_lattice(&board, &pieces, true);
+        let unique = filter_out_lattice_duplicates(&board, &pieces, &solutions, true);
+        assert_eq!(unique.len(), 156);
+        assert_eq!(solutions.len(), 4 * 156);
+    }
+
+    #[test]
+    fn triangles_are_letters_without_colours() {
+        let board = LatticeBoard::parallelogram(Lattice::Triangle, 2, 1);
+        let plain = Terminal {
+            glyphs: Glyphs::Ascii,
+            colors: ColorDepth::None,
+        };
+
+        assert_eq!(
+            render_lattice_world(&board, &[1, 1, 2, 0], plain),
+            vec!["AAB."]
+        );
+    }
+}
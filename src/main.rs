@@ -1,24 +1,70 @@
+mod analytics;
+mod bench;
 mod block;
+mod board;
+mod browser;
+mod calendar;
+mod cover;
+mod difficulty;
 mod discovery;
+mod distributed;
 mod draw;
+mod encoding;
+mod export;
 mod filtering;
 mod game;
+mod generator;
+mod lattice;
+mod palette;
+mod placement_table;
+mod polycube;
+mod rectangles;
+mod server;
+mod subsets;
+mod symmetry;
+mod terminal;
 mod validators;
+mod verifier;
+mod world;
 
-use std::{io::stdin, thread, time::Instant};
+use std::{
+    env, fs,
+    io::stdin,
+    net::TcpListener,
+    process,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
 
+use analytics::analyse;
 use block::Block;
-use filtering::{filter_out_duplicates, world_id};
+use board::{preset_boards, Board};
+use browser::browse;
+use calendar::{CalendarBoard, DEFAULT_LAYOUT, MONTHS};
+use difficulty::rate;
+use export::AtMostOne;
+use filtering::{solution_classes, SolutionClass};
 use game::Game;
-use validators::{valid_inventory, Valid};
+use generator::{generate_challenge, Rng};
+use lattice::{
+    draw_lattice_world, filter_out_lattice_duplicates, polyforms, solve_lattice, square_blocks,
+    square_values, Lattice, LatticeBoard,
+};
+use palette::{parse_palette, set_style, ColorScheme, Style};
+use polycube::{draw_world3, filter_out_duplicates3, solve3, soma_set, Board3, Polycube};
+use symmetry::{transform_world, Symmetry};
+use terminal::{detect_terminal, set_terminal, ColorDepth, Glyphs};
+use validators::{geometry_problems, valid_inventory, valid_polycubes, valid_polyforms, Valid};
+use world::{id_char, Topology, World, HOLE};
 
 use crate::{
-    discovery::{add_possibilities, next_possible_placements},
-    draw::draw_world,
+    discovery::{
+        add_possibilities_until, next_possible_placements_with_stats, CancelToken, SearchStats,
+    },
+    draw::{draw_labelled_world, draw_solution, RenderMode},
 };
 
-const WORLD_HEIGHT: usize = 8;
-const WORLD_WIDTH: usize = 8;
 const COLORS: [(u8, u8, u8); 7] = [
     (241, 196, 15),
     (41, 128, 185),
@@ -31,83 +77,130 @@ const COLORS: [(u8, u8, u8); 7] = [
 
 type Position = (usize, usize);
 type Offset = (i32, i32);
-type World = [[usize; WORLD_WIDTH]; WORLD_HEIGHT];
 type Inventory = Vec<Block>; // blocks to choose from
 
-enum Rotation {
-    Zero,
-    Ninety,
-    OneEighty,
-    TwoSeventy,
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct Placement {
     block: Block,
     rotation: usize,
     anchor_pos: Position,
 }
 
-#[allow(unused)]
-fn calculate_parallel(inventory: Inventory) -> Vec<World> {
+pub struct SolveResult {
+    pub classes: Vec<SolutionClass>,
+    pub raw_solutions: usize,
+    pub stats: SearchStats,
+    pub complete: bool, // false when the search was cancelled, `classes` are what was found so far
+}
+
+/// Searches until every solution is found, without printing progress.
+fn calculate_parallel(board: &Board, inventory: Inventory) -> SolveResult {
+    if let Valid::Invalid(reason) = valid_inventory(&inventory) {
+        panic!("Invalid inventory: {}", reason);
+    }
+
+    let game = Game::new(board, inventory);
+    calculate_parallel_from(board, game, &CancelToken::default(), |_, _, _| {})
+}
+
+fn calculate_parallel_until(
+    board: &Board,
+    inventory: Inventory,
+    token: &CancelToken,
+) -> SolveResult {
     if let Valid::Invalid(reason) = valid_inventory(&inventory) {
         panic!("Invalid inventory: {}", reason);
     }
 
-    let game = Game::new(inventory);
+    let game = Game::new(board, inventory);
+    calculate_parallel_from(board, game, token, print_thread_progress)
+}
 
-    let mut threads = vec![];
+fn print_thread_progress(found: &[World], done: usize, total: usize) {
     println!(
-        "spawning {} threads...",
-        next_possible_placements(&game).len()
+        "Thread {}/{} finished and found {} solutions!",
+        done,
+        total,
+        found.len()
     );
+    if done == total {
+        println!("Filtering out duplicates...");
+    }
+}
+
+/// Searches from `game` with a thread per possible first placement. `on_thread_done` is called
+/// with the solutions of every thread as soon as it finishes, together with the number of finished
+/// and spawned threads. Prints nothing, so it can run in the background.
+fn calculate_parallel_from(
+    board: &Board,
+    game: Game,
+    token: &CancelToken,
+    mut on_thread_done: impl FnMut(&[World], usize, usize),
+) -> SolveResult {
+    let mut stats = SearchStats::default();
+    let first_placements = next_possible_placements_with_stats(&game, &mut stats);
+    let n_threads = first_placements.len();
+    let (sender, receiver) = mpsc::channel();
 
-    for p in next_possible_placements(&game) {
+    for (i, p) in first_placements.into_iter().enumerate() {
         let mut g = game.clone();
         g.place_block(p);
+        let token = token.clone();
+        let sender = sender.clone();
 
-        threads.push(thread::spawn(move || {
+        thread::spawn(move || {
             let mut solved_worlds = Vec::new();
-            add_possibilities(&mut g, &mut solved_worlds);
+            let mut stats = SearchStats::default();
+            add_possibilities_until(&mut g, &mut solved_worlds, &mut stats, &token);
 
-            solved_worlds
-        }));
+            sender.send((i, solved_worlds, stats)).unwrap();
+        });
     }
+    drop(sender);
 
-    let mut solved_worlds = Vec::new();
-    let n_threads = threads.len();
-    for (i, t) in threads.into_iter().enumerate() {
-        let mut found_in_thread = t.join().unwrap();
-        println!(
-            "Thread {}/{} finished and found {} solutions!",
-            i + 1,
-            n_threads,
-            found_in_thread.len()
-        );
-        solved_worlds.append(&mut found_in_thread);
+    // kept in the order of the first placements, so the result does not depend on timing
+    let mut found_per_thread = vec![Vec::new(); n_threads];
+    for done in 1..=n_threads {
+        let (i, found_in_thread, thread_stats) = receiver.recv().expect("A search thread panicked");
+        stats.merge(&thread_stats);
+        on_thread_done(&found_in_thread, done, n_threads);
+        found_per_thread[i] = found_in_thread;
     }
+    let solved_worlds: Vec<World> = found_per_thread.into_iter().flatten().collect();
 
-    println!("Filtering out duplicates...");
-    filter_out_duplicates(&solved_worlds)
+    SolveResult {
+        classes: solution_classes(board, &solved_worlds),
+        raw_solutions: solved_worlds.len(),
+        stats,
+        complete: !token.stopped_early(),
+    }
+}
+
+fn calculate_sync(board: &Board, inventory: Inventory) -> SolveResult {
+    calculate_sync_until(board, inventory, &CancelToken::default())
 }
 
-#[allow(unused)]
-fn calculate_sync(inventory: Inventory) -> Vec<World> {
+fn calculate_sync_until(board: &Board, inventory: Inventory, token: &CancelToken) -> SolveResult {
     if let Valid::Invalid(reason) = valid_inventory(&inventory) {
         panic!("Invalid inventory: {}", reason);
     }
 
-    let mut game = Game::new(inventory);
+    let mut game = Game::new(board, inventory);
 
     let mut solved_worlds = Vec::new();
-    add_possibilities(&mut game, &mut solved_worlds);
+    let mut stats = SearchStats::default();
+    add_possibilities_until(&mut game, &mut solved_worlds, &mut stats, token);
 
-    println!("Filtering out duplicates...");
-    filter_out_duplicates(&solved_worlds)
+    SolveResult {
+        classes: solution_classes(board, &solved_worlds),
+        raw_solutions: solved_worlds.len(),
+        stats,
+        complete: !token.stopped_early(),
+    }
 }
 
-fn main() {
-    let inventory: Inventory = vec![
+fn default_inventory() -> Inventory {
+    vec![
         Block::Line(1),
         Block::Bridge(2),
         Block::T(3),
@@ -121,33 +214,972 @@ fn main() {
         Block::Z(11),
         Block::Stairs(12),
         Block::L(13),
-    ];
+    ]
+}
+
+/// The seven pentominoes of the classic calendar puzzle with the T and the W, which take the
+/// place of its 2x3 rectangle and the cells `DEFAULT_LAYOUT` adds. Every date of that variant has
+/// a solution with them.
+fn calendar_inventory() -> Inventory {
+    vec![
+        Block::Bridge(2),
+        Block::T(3),
+        Block::Corner(5),
+        Block::DoubleL(6),
+        Block::Baton(8),
+        Block::Cursor(9),
+        Block::Z(11),
+        Block::Stairs(12),
+        Block::L(13),
+    ]
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|idx| args.get(idx + 1))
+        .map(|value| value.as_str())
+}
+
+/// Options every command accepts that take a value, see `terminal_options` and `style_options`.
+const STYLE_OPTIONS: [&str; 4] = ["--glyphs", "--colors", "--colors-by", "--palette"];
+
+/// The arguments that are not options, `value_options` are followed by a value to skip as well.
+fn positional_args<'a>(args: &'a [String], value_options: &[&str]) -> Vec<&'a str> {
+    let mut positional = Vec::new();
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        if value_options.contains(&arg.as_str()) || STYLE_OPTIONS.contains(&arg.as_str()) {
+            args_iter.next();
+        } else if !arg.starts_with("--") {
+            positional.push(arg.as_str());
+        }
+    }
+    positional
+}
+
+/// Blocks of the default inventory picked with `--blocks 1,2,3`, all of them by default.
+fn inventory_option(args: &[String]) -> Inventory {
+    match option_value(args, "--blocks") {
+        Some(ids) => {
+            let ids: Vec<usize> = ids
+                .split(',')
+                .map(|id| id.parse().expect("Block ids should be numbers"))
+                .collect();
+            default_inventory()
+                .into_iter()
+                .filter(|block| ids.contains(&block.get_block_id()))
+                .collect()
+        }
+        None => default_inventory(),
+    }
+}
+
+/// Board read from `--board <file>`, the 8x8 board by default. `--topology cylinder|torus` joins
+/// its edges, overriding a `topology` line in the file.
+fn board_option(args: &[String]) -> Board {
+    let board = match option_value(args, "--board") {
+        Some(path) => Board::parse(&fs::read_to_string(path).unwrap()).unwrap(),
+        None => Board::rectangle(8, 8),
+    };
+
+    match option_value(args, "--topology") {
+        Some(name) => board.with_topology(Topology::parse(name).unwrap()),
+        None => board,
+    }
+}
+
+/// The game to solve on `board`. The `--board` file may already have blocks on it, written as
+/// their letters like the challenges of `generate`, those are taken out of the inventory.
+fn game_option(args: &[String], board: &Board, inventory: Inventory) -> Game {
+    match option_value(args, "--board") {
+        Some(path) => game_from_file(path, board, inventory),
+        None => Game::new(board, inventory),
+    }
+}
+
+/// The game in a board file, with the blocks already on it taken out of the inventory.
+fn game_from_file(path: &str, board: &Board, inventory: Inventory) -> Game {
+    let world = World::parse(&fs::read_to_string(path).unwrap())
+        .unwrap()
+        .with_topology(board.topology());
+    if let Some(id) = world
+        .rows()
+        .flatten()
+        .find(|v| **v != 0 && **v != HOLE && !inventory.iter().any(|b| b.get_block_id() == **v))
+    {
+        panic!(
+            "Block {} is on the board but not in the inventory",
+            id_char(*id)
+        );
+    }
+
+    Game::from_world(board, world, inventory)
+}
+
+/// Stops the search after `--timeout <seconds>` or once `--max-solutions <n>` raw solutions were
+/// found.
+fn cancel_option(args: &[String]) -> CancelToken {
+    let timeout = option_value(args, "--timeout").map(|seconds| {
+        Duration::from_secs_f64(
+            seconds
+                .parse()
+                .expect("Timeout should be a number of seconds"),
+        )
+    });
+    let max_solutions = option_value(args, "--max-solutions").map(|n| {
+        n.parse()
+            .expect("Maximum number of solutions should be a number")
+    });
+
+    CancelToken::new(timeout, max_solutions)
+}
+
+/// `--letters` draws solutions with a letter per cell instead of colours.
+fn render_mode_option(args: &[String]) -> RenderMode {
+    if args.iter().any(|arg| arg == "--letters") {
+        RenderMode::Letters
+    } else {
+        RenderMode::Color
+    }
+}
 
-    // let inventory: Inventory = vec![
-    //     Block::Square(1),
-    //     Block::Square(2),
-    //     Block::Square(3),
-    //     Block::Square(4),
-    // ];
+fn solve(args: &[String]) {
+    let mode = render_mode_option(args);
+    let board = board_option(args);
+    let inventory = inventory_option(args);
 
     let start = Instant::now();
 
-    let solved_worlds = calculate_parallel(inventory);
-    // let solved_worlds = calculate_sync(inventory);
+    if let Valid::Invalid(reason) = valid_inventory(&inventory) {
+        panic!("Invalid inventory: {}", reason);
+    }
+    let game = game_option(args, &board, inventory.clone());
+    let result = calculate_parallel_from(&board, game, &cancel_option(args), print_thread_progress);
+    let solution_classes = result.classes;
 
     let end = Instant::now();
 
     println!(
         "Found {} unique solution(s) in {:?}",
-        solved_worlds.len(),
+        solution_classes.len(),
         end.duration_since(start),
     );
+    if !result.complete {
+        println!("The search was stopped early, there may be more solutions");
+    }
+    println!(
+        "{} of them are self-symmetric",
+        solution_classes
+            .iter()
+            .filter(|c| c.is_self_symmetric())
+            .count()
+    );
+
+    let notes = solution_classes
+        .iter()
+        .map(|class| class.summary())
+        .collect();
+    let solutions = solution_classes
+        .into_iter()
+        .map(|class| class.canonical)
+        .collect();
+    browse(&board, solutions, notes, inventory, mode);
+}
+
+fn generate(args: &[String]) {
+    let mut rng = match option_value(args, "--seed") {
+        Some(seed) => Rng::new(seed.parse().expect("Seed should be a number")),
+        None => Rng::from_time(),
+    };
+    let min_pre_placed = option_value(args, "--pre-placed")
+        .map(|n| {
+            n.parse()
+                .expect("Number of pre-placed blocks should be a number")
+        })
+        .unwrap_or(4);
+    let out = option_value(args, "--out").unwrap_or("challenge");
+
+    let board = match option_value(args, "--board") {
+        Some(path) => Board::parse(&fs::read_to_string(path).unwrap()).unwrap(),
+        None => {
+            let boards = preset_boards();
+            boards[rng.below(boards.len())].clone()
+        }
+    };
+
+    println!("Generating challenge...");
+    let inventory = inventory_option(args);
+    let challenge = match generate_challenge(&board, &inventory, min_pre_placed, &mut rng) {
+        Some(challenge) => challenge,
+        None => {
+            println!("Could not create a challenge for this board");
+            return;
+        }
+    };
+
+    println!(
+        "Challenge with {} pre-placed block(s) and a unique solution:",
+        challenge.pre_placed.len()
+    );
+    draw_solution(
+        &challenge.start,
+        &challenge.inventory,
+        render_mode_option(args),
+    );
+    println!("Blocks: {:?}", challenge.inventory);
+
+    // the pre-placed blocks are written as their letters, `game_option` puts them back
+    fs::write(format!("{}.board", out), challenge.start.to_text()).unwrap();
+    fs::write(format!("{}.solution", out), challenge.solution.to_text()).unwrap();
+    let ids: Vec<String> = challenge
+        .inventory
+        .iter()
+        .map(|block| block.get_block_id().to_string())
+        .collect();
+    println!("Written to {}.board and {}.solution", out, out);
+    println!(
+        "Solve it with --board {}.board --blocks {}",
+        out,
+        ids.join(",")
+    );
+}
+
+fn difficulty(args: &[String]) {
+    let path = args
+        .first()
+        .expect("Usage: difficulty <board file> [--blocks <ids>] [--json]");
+    let board = Board::parse(&fs::read_to_string(path).unwrap()).unwrap();
+    let inventory = inventory_option(args);
+    let game = game_from_file(path, &board, inventory.clone());
+
+    let difficulty = rate(&board, &game);
+
+    if args.iter().any(|arg| arg == "--json") {
+        println!("{}", difficulty.to_json());
+    } else {
+        draw_solution(&game.world, &inventory, render_mode_option(args));
+        println!("{}", difficulty);
+    }
+}
+
+fn cube(args: &[String]) {
+    let size: Vec<usize> = option_value(args, "--size")
+        .unwrap_or("3x3x3")
+        .split('x')
+        .map(|n| n.parse().expect("Size should look like 3x4x5"))
+        .collect();
+    let board = Board3::cuboid(size[0], size[1], size[2]);
+    let reflections = args.iter().any(|arg| arg == "--reflections");
+
+    let pieces: Vec<Polycube> = match option_value(args, "--pieces").unwrap_or("soma") {
+        "soma" => soma_set(),
+        "pentominoes" => default_inventory()
+            .iter()
+            .filter(|block| block.get_size() == 5)
+            .map(Polycube::from_block)
+            .collect(),
+        other => panic!("Unknown piece set {}", other),
+    };
+
+    if let Valid::Invalid(reason) = valid_polycubes(&board, &pieces) {
+        panic!("Invalid pieces: {}", reason);
+    }
+
+    let start = Instant::now();
+    let solved_worlds = solve3(&board, &pieces, reflections);
+    println!("Filtering out duplicates...");
+    let unique_worlds = filter_out_duplicates3(&board, &pieces, &solved_worlds, reflections);
+
+    println!(
+        "Found {} unique solution(s) ({} in total) in {:?}",
+        unique_worlds.len(),
+        solved_worlds.len(),
+        Instant::now().duration_since(start),
+    );
+
+    for world in unique_worlds.iter() {
+        println!("Press enter to view (another) solution");
+        let mut _buf = String::new();
+        stdin().read_line(&mut _buf).unwrap();
+        draw_world3(world);
+    }
+}
+
+fn lattice(args: &[String]) {
+    let lattice = match option_value(args, "--grid").unwrap_or("hex") {
+        "square" => Lattice::Square,
+        "hex" => Lattice::Hex,
+        "triangle" => Lattice::Triangle,
+        other => panic!("Unknown grid {}", other),
+    };
+    let piece_size = option_value(args, "--piece-size")
+        .map(|n| n.parse().expect("Piece size should be a number"))
+        .unwrap_or(4);
+    let size: Vec<usize> = option_value(args, "--size")
+        .unwrap_or("7x4")
+        .split('x')
+        .map(|n| n.parse().expect("Size should look like 7x4"))
+        .collect();
+    let reflections = !args.iter().any(|arg| arg == "--no-reflections");
+
+    let board = LatticeBoard::parallelogram(lattice, size[0], size[1]);
+    let pieces = polyforms(lattice, piece_size);
+
+    if let Valid::Invalid(reason) = valid_polyforms(&board, &pieces) {
+        panic!("Invalid pieces: {}", reason);
+    }
+
+    let start = Instant::now();
+    // the block solver always allows mirror images
+    let blocks = match (lattice, reflections) {
+        (Lattice::Square, true) => square_blocks(&pieces),
+        _ => None,
+    };
+    let (unique_solutions, n_solutions) = match blocks {
+        Some(inventory) => {
+            // the solver fills row by row, it prunes much sooner on narrow rows
+            let turned = size[0] > size[1];
+            let rectangle = Board::rectangle(size[0].min(size[1]), size[0].max(size[1]));
+            let result = calculate_parallel_until(&rectangle, inventory, &CancelToken::default());
+            let unique: Vec<Vec<usize>> = result
+                .classes
+                .iter()
+                .map(|class| match turned {
+                    true => transform_world(&class.canonical, Symmetry::Diagonal),
+                    false => class.canonical.clone(),
+                })
+                .map(|world| square_values(&board, &world))
+                .collect();
+            (unique, result.raw_solutions)
+        }
+        None => {
+            let solutions = solve_lattice(&board, &pieces, reflections);
+            println!("Filtering out duplicates...");
+            let unique = filter_out_lattice_duplicates(&board, &pieces, &solutions, reflections);
+            (unique, solutions.len())
+        }
+    };
+
+    println!(
+        "Found {} unique solution(s) ({} in total) in {:?}",
+        unique_solutions.len(),
+        n_solutions,
+        Instant::now().duration_since(start),
+    );
 
-    for s in solved_worlds.iter() {
+    for solution in unique_solutions.iter() {
         println!("Press enter to view (another) solution");
         let mut _buf = String::new();
         stdin().read_line(&mut _buf).unwrap();
-        draw_world(s);
-        println!("id: {}", world_id(s));
+        draw_lattice_world(&board, solution);
+    }
+}
+
+fn stats(args: &[String]) {
+    let board = board_option(args);
+    let inventory = inventory_option(args);
+
+    let result = calculate_parallel_until(&board, inventory.clone(), &cancel_option(args));
+    if !result.complete {
+        println!("The search was stopped early, only the solutions found so far are analysed");
+    }
+    let solutions: Vec<World> = result
+        .classes
+        .into_iter()
+        .map(|class| class.members[0].world.clone())
+        .collect();
+    let analysis = analyse(&inventory, &solutions);
+
+    println!("Analysis of {} unique solution(s)", analysis.n_solutions);
+    analysis.draw_heatmaps();
+
+    if let Some(prefix) = option_value(args, "--csv") {
+        fs::write(format!("{}_heatmap.csv", prefix), analysis.heatmap_csv()).unwrap();
+        fs::write(
+            format!("{}_orientations.csv", prefix),
+            analysis.orientations_csv(),
+        )
+        .unwrap();
+        fs::write(
+            format!("{}_adjacency.csv", prefix),
+            analysis.adjacency_csv(),
+        )
+        .unwrap();
+        println!("Written to {}_*.csv", prefix);
+    }
+}
+
+/// Searches one work unit, see `distributed::work_units`. Without `--unit` it only tells how many
+/// units there are.
+fn worker(args: &[String]) {
+    let board = board_option(args);
+    let inventory = inventory_option(args);
+    let depth = option_value(args, "--depth")
+        .map(|n| n.parse().expect("Depth should be a number"))
+        .unwrap_or(2);
+
+    let unit = match option_value(args, "--unit") {
+        Some(unit) => unit.parse().expect("Unit should be a number"),
+        None => {
+            let game = Game::new(&board, inventory);
+            match distributed::work_units(&game, depth).len() {
+                0 => println!("No units at depth {}, the search ends before it", depth),
+                n_units => println!(
+                    "{} units at depth {}, numbered 0 to {}",
+                    n_units,
+                    depth,
+                    n_units - 1
+                ),
+            }
+            return;
+        }
+    };
+
+    let result = match distributed::run_unit(&board, inventory, depth, unit) {
+        Ok(result) => result,
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
+    };
+    let default_out = format!("unit-{}.result", unit);
+    let out = option_value(args, "--out").unwrap_or(&default_out);
+    fs::write(out, result.to_text()).unwrap();
+    println!(
+        "Unit {}/{} found {} solution(s), written to {}",
+        unit,
+        result.n_units,
+        result.solutions.len(),
+        out
+    );
+}
+
+/// Combines the result files of all units of a search, `--out` writes the unique solutions.
+fn merge(args: &[String]) {
+    let mut results = Vec::new();
+    for arg in positional_args(args, &["--out"]) {
+        let text = fs::read_to_string(arg).unwrap();
+        results.push(
+            distributed::UnitResult::parse(&text).unwrap_or_else(|e| panic!("{}: {}", arg, e)),
+        );
+    }
+
+    let (_board, unique) = match distributed::merge(&results) {
+        Ok(merged) => merged,
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
+    };
+    let raw: usize = results.iter().map(|r| r.solutions.len()).sum();
+    println!(
+        "Found {} unique solution(s) ({} in total)",
+        unique.len(),
+        raw
+    );
+
+    if let Some(out) = option_value(args, "--out") {
+        let text: Vec<String> = unique.iter().map(|world| world.to_text()).collect();
+        fs::write(out, text.join("\n")).unwrap();
+        println!("Written to {}", out);
+    }
+}
+
+/// Writes the puzzle for external solvers, `export cnf|cover [--encoding pairwise|sequential]`.
+fn export(args: &[String]) {
+    let board = board_option(args);
+    let inventory = inventory_option(args);
+
+    let text = match args.first().map(|arg| arg.as_str()) {
+        Some("cnf") => {
+            let encoding = option_value(args, "--encoding").unwrap_or("pairwise");
+            export::to_dimacs(&board, &inventory, AtMostOne::parse(encoding).unwrap())
+        }
+        Some("cover") => export::to_exact_cover(&board, &inventory),
+        _ => panic!("Usage: export cnf|cover [--encoding pairwise|sequential] [--out file]"),
+    };
+
+    match option_value(args, "--out") {
+        Some(path) => {
+            fs::write(path, text).unwrap();
+            println!("Written to {}", path);
+        }
+        None => print!("{}", text),
+    }
+}
+
+/// Shows what an external solver found, `import cnf <model file>` or `import cover <rows file>`
+/// with the same board and blocks as the export.
+fn import(args: &[String]) {
+    let usage = "Usage: import cnf|cover <file>";
+    let text = fs::read_to_string(args.get(1).expect(usage)).unwrap();
+    let board = board_option(args);
+    let inventory = inventory_option(args);
+
+    let world = match args[0].as_str() {
+        "cnf" => export::from_sat_model(&board, &inventory, &text),
+        "cover" => export::from_exact_cover(&board, &inventory, &text),
+        _ => panic!("{}", usage),
+    };
+
+    match world {
+        Ok(world) => draw_solution(&world, &inventory, render_mode_option(args)),
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
+    }
+}
+
+/// Tries every subset of the blocks with the area of the board, `--tiling` only lists the ones
+/// that fill it.
+fn subsets(args: &[String]) {
+    let board = board_option(args);
+    let inventory = inventory_option(args);
+    let only_tiling = args.iter().any(|arg| arg == "--tiling");
+
+    let results = match subsets::tiling_subsets(&board, &inventory, &cancel_option(args)) {
+        Ok(results) => results,
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
+    };
+
+    for result in results.iter() {
+        if only_tiling && !result.tiles() {
+            continue;
+        }
+        let letters: String = result.blocks.iter().map(|b| b.get_letter()).collect();
+        match (result.tiles(), result.complete) {
+            (_, false) => println!("{}: stopped early", letters),
+            (true, true) => println!(
+                "{}: {} unique solution(s)",
+                letters, result.unique_solutions
+            ),
+            (false, true) => println!("{}: no tiling", letters),
+        }
+    }
+
+    println!(
+        "{} of {} subsets with {} cells tile the board",
+        results.iter().filter(|r| r.tiles()).count(),
+        results.len(),
+        board.n_cells()
+    );
+}
+
+/// Lists the rectangles the blocks can fill. `--unlimited` allows any number of copies of every
+/// block, then `--max-area` is needed.
+fn rectangles(args: &[String]) {
+    let inventory = inventory_option(args);
+    let unlimited = args.iter().any(|arg| arg == "--unlimited");
+    let max_area = option_value(args, "--max-area")
+        .map(|n| n.parse().expect("Maximum area should be a number"));
+
+    let results = match rectangles::tileable_rectangles(&inventory, unlimited, max_area) {
+        Ok(results) => results,
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
+    };
+
+    for result in results.iter().filter(|r| r.tiles()) {
+        println!(
+            "{}x{}: {} unique solution(s)",
+            result.width, result.height, result.unique_solutions
+        );
+    }
+    println!(
+        "{} of {} rectangles can be filled",
+        results.iter().filter(|r| r.tiles()).count(),
+        results.len()
+    );
+}
+
+/// Covers a calendar board except for today's date, `--date 3-14`, or the cells in
+/// `--targets Jan,14`. `--all` counts the solutions of every date. `--layout` reads another
+/// board, see `CalendarBoard::parse`.
+fn calendar(args: &[String]) {
+    let layout = match option_value(args, "--layout") {
+        Some(path) => fs::read_to_string(path).unwrap(),
+        None => {
+            println!("Using a 47 cell variant of the puzzle-a-day layout, not the original one");
+            DEFAULT_LAYOUT.to_string()
+        }
+    };
+    let calendar = CalendarBoard::parse(&layout).unwrap();
+    let inventory = match option_value(args, "--blocks") {
+        Some(_) => inventory_option(args),
+        None => calendar_inventory(),
+    };
+
+    if args.iter().any(|arg| arg == "--all") {
+        let results = calendar::solve_all_dates(&calendar, &inventory).unwrap();
+        for result in results.iter() {
+            println!(
+                "{} {}: {} solution(s)",
+                MONTHS[result.month - 1],
+                result.day,
+                result.solutions
+            );
+        }
+
+        let unsolvable: Vec<String> = results
+            .iter()
+            .filter(|r| r.solutions == 0)
+            .map(|r| format!("{} {}", MONTHS[r.month - 1], r.day))
+            .collect();
+        let counts = results.iter().map(|r| r.solutions);
+        println!(
+            "Between {} and {} solutions per date, {} date(s) without solution{}",
+            counts.clone().min().unwrap(),
+            counts.max().unwrap(),
+            unsolvable.len(),
+            match unsolvable.len() {
+                0 => String::new(),
+                _ => format!(": {}", unsolvable.join(", ")),
+            }
+        );
+        return;
+    }
+
+    let targets = match option_value(args, "--targets") {
+        Some(labels) => labels
+            .split(',')
+            .map(|label| calendar.find(label))
+            .collect::<Result<Vec<Position>, String>>()
+            .unwrap(),
+        None => {
+            let (month, day) = match option_value(args, "--date") {
+                Some(date) => calendar::parse_date(date).unwrap(),
+                None => calendar::today(),
+            };
+            calendar.date_targets(month, day).unwrap()
+        }
+    };
+    let labels: Vec<&str> = targets
+        .iter()
+        .map(|pos| calendar.labels.get(pos).map_or("?", |l| l.as_str()))
+        .collect();
+
+    let solutions = calendar::solve_targets(&calendar, &targets, inventory);
+    println!(
+        "Found {} solution(s) leaving {} uncovered",
+        solutions.len(),
+        labels.join(" ")
+    );
+    if let Some(world) = solutions.first() {
+        draw_labelled_world(world, &calendar.labels);
+    }
+}
+
+fn serve(args: &[String]) {
+    let addr = option_value(args, "--addr").unwrap_or("127.0.0.1:8080");
+    let listener = TcpListener::bind(addr).unwrap();
+    println!("Listening on http://{}", listener.local_addr().unwrap());
+
+    server::serve(listener);
+}
+
+fn verify(args: &[String]) {
+    let path = args
+        .first()
+        .expect("Usage: verify <solution file> [--blocks 1,2,3]");
+    let text = fs::read_to_string(path).unwrap();
+
+    let inventory = inventory_option(args);
+
+    match verifier::verify(&text, &inventory) {
+        Ok(verified) => {
+            draw_solution(&verified.world, &inventory, render_mode_option(args));
+            println!("Valid solution");
+            println!("id: {}", verified.id);
+            println!("code: {}", verified.code);
+        }
+        Err(errors) => {
+            for error in errors.iter() {
+                println!("{}", error);
+            }
+            process::exit(1);
+        }
+    }
+}
+
+fn validate_pieces() {
+    let mut all_valid = true;
+
+    for block in default_inventory() {
+        let problems = geometry_problems(&block);
+        if problems.is_empty() {
+            println!(
+                "{:?}: ok ({} orientations)",
+                block,
+                block.get_geometries().len()
+            );
+        }
+        for problem in problems {
+            println!("{:?}: {}", block, problem);
+            all_valid = false;
+        }
+    }
+
+    if !all_valid {
+        process::exit(1);
+    }
+}
+
+/// Runs the reference puzzles in every solver mode, `--only <name>` picks one, the JSON report
+/// goes to `--out` (bench.json by default).
+fn bench(args: &[String]) {
+    let only = option_value(args, "--only");
+    let mut results = Vec::new();
+
+    for puzzle in bench::reference_puzzles() {
+        if only.is_some_and(|name| name != puzzle.name) {
+            continue;
+        }
+
+        for mode in bench::SOLVER_MODES {
+            let result = bench::run(&puzzle, mode);
+            println!(
+                "{} ({}): {} unique, {} nodes, {} placements tested, {:.1} ms, {:.1} solutions/s",
+                result.puzzle,
+                mode.name(),
+                result.unique_solutions,
+                result.nodes,
+                result.placements_tested,
+                result.wall_time_ms,
+                result.solutions_per_second(),
+            );
+            results.push(result);
+        }
+    }
+
+    let path = option_value(args, "--out").unwrap_or("bench.json");
+    fs::write(path, bench::report_json(&results)).unwrap();
+    println!("Report written to {}", path);
+}
+
+/// Overrides what was detected about the terminal with `--glyphs legacy|box|ascii`,
+/// `--colors truecolor|256|16|none` and `--no-color`.
+fn terminal_options(args: &[String]) {
+    let mut terminal = detect_terminal();
+
+    if let Some(name) = option_value(args, "--glyphs") {
+        terminal.glyphs = Glyphs::parse(name).unwrap();
+    }
+    if let Some(name) = option_value(args, "--colors") {
+        terminal.colors = ColorDepth::parse(name).unwrap();
+    }
+    if args.iter().any(|arg| arg == "--no-color") {
+        terminal.colors = ColorDepth::None;
+    }
+
+    if terminal.colors == ColorDepth::None {
+        colored::control::set_override(false);
+    }
+    set_terminal(terminal);
+}
+
+/// `--colors-by kind` keeps the colour of every kind of block the same across solutions,
+/// `--palette` replaces the colours with a named palette or a list like `#f1c40f,#2980b9`.
+fn style_options(args: &[String]) {
+    let scheme = match option_value(args, "--colors-by").unwrap_or("adjacency") {
+        "adjacency" => ColorScheme::Adjacency,
+        "kind" => ColorScheme::ByKind,
+        other => panic!("Unknown colour scheme {}, use adjacency or kind", other),
+    };
+    let palette = option_value(args, "--palette").map(|text| parse_palette(text).unwrap());
+
+    set_style(Style { scheme, palette });
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    terminal_options(&args);
+    style_options(&args);
+
+    match args.first().map(|arg| arg.as_str()) {
+        Some("generate") => generate(&args[1..]),
+        Some("difficulty") => difficulty(&args[1..]),
+        Some("cube") => cube(&args[1..]),
+        Some("lattice") => lattice(&args[1..]),
+        Some("stats") => stats(&args[1..]),
+        Some("bench") => bench(&args[1..]),
+        Some("validate-pieces") => validate_pieces(),
+        Some("verify") => verify(&args[1..]),
+        Some("serve") => serve(&args[1..]),
+        Some("worker") => worker(&args[1..]),
+        Some("merge") => merge(&args[1..]),
+        Some("export") => export(&args[1..]),
+        Some("import") => import(&args[1..]),
+        Some("subsets") => subsets(&args[1..]),
+        Some("rectangles") => rectangles(&args[1..]),
+        Some("calendar") => calendar(&args[1..]),
+        _ => solve(&args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pentominoes() -> Inventory {
+        default_inventory()
+            .into_iter()
+            .filter(|block| block.get_size() == 5)
+            .collect()
+    }
+
+    fn centre_hole_board() -> Board {
+        Board::rectangle(8, 8).with_holes(&[(3, 3), (4, 3), (3, 4), (4, 4)])
+    }
+
+    #[test]
+    fn wrapped_boards_have_solutions_across_the_seam() {
+        let inventory: Inventory = (1..=4).map(Block::Square).collect();
+        let cylinder = Board::rectangle(4, 4).with_topology(Topology::Cylinder);
+        let torus = Board::rectangle(4, 4).with_topology(Topology::Torus);
+
+        // the two rows of squares can each be shifted by one
+        let result = calculate_sync(&cylinder, inventory.clone());
+        assert_eq!(result.raw_solutions, 4 * 24);
+        // and on a torus also the columns, or both
+        let result = calculate_parallel(&torus, inventory);
+        assert_eq!(result.raw_solutions, 12 * 24);
+        // the grid and the brick pattern, each with three ways to put the squares on them
+        assert_eq!(result.classes.len(), 6);
+
+        // every shift of the line covers the same cells
+        let cylinder = Board::rectangle(5, 1).with_topology(Topology::Cylinder);
+        assert_eq!(
+            calculate_sync(&cylinder, vec![Block::Line(1)]).raw_solutions,
+            1
+        );
+    }
+
+    #[test]
+    fn squares_on_4x4() {
+        let inventory: Inventory = (1..=4).map(Block::Square).collect();
+
+        assert_eq!(
+            calculate_sync(&Board::rectangle(4, 4), inventory.clone())
+                .classes
+                .len(),
+            3
+        );
+        assert_eq!(
+            calculate_parallel(&Board::rectangle(4, 4), inventory)
+                .classes
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn searches_stop_at_the_solution_limit() {
+        let token = CancelToken::new(None, Some(10));
+        let result = calculate_sync_until(&centre_hole_board(), pentominoes(), &token);
+        assert_eq!(result.raw_solutions, 10);
+        assert!(!result.complete);
+
+        let token = CancelToken::new(None, Some(10));
+        let result = calculate_parallel_until(&centre_hole_board(), pentominoes(), &token);
+        assert_eq!(result.raw_solutions, 10);
+        assert!(!result.complete);
+
+        // a limit of exactly the number of solutions does not cut the search short
+        let inventory: Inventory = (1..=4).map(Block::Square).collect();
+        let token = CancelToken::new(None, Some(24));
+        let result = calculate_sync_until(&Board::rectangle(4, 4), inventory.clone(), &token);
+        assert_eq!(result.raw_solutions, 24);
+        assert!(result.complete);
+
+        let token = CancelToken::new(None, Some(24));
+        let result = calculate_parallel_until(&Board::rectangle(4, 4), inventory.clone(), &token);
+        assert_eq!(result.raw_solutions, 24);
+        assert!(result.complete);
+
+        let token = CancelToken::new(None, Some(23));
+        let result = calculate_sync_until(&Board::rectangle(4, 4), inventory, &token);
+        assert_eq!(result.raw_solutions, 23);
+        assert!(!result.complete);
+    }
+
+    #[test]
+    fn searches_stop_when_cancelled() {
+        let token = CancelToken::new(Some(Duration::ZERO), None);
+        let result = calculate_parallel_until(&centre_hole_board(), pentominoes(), &token);
+        assert!(!result.complete);
+        assert!(result.stats.nodes < 1024 * 100);
+
+        let token = CancelToken::default();
+        token.cancel();
+        let result = calculate_sync_until(&centre_hole_board(), pentominoes(), &token);
+        assert_eq!(result.raw_solutions, 0);
+        assert!(!result.complete);
+
+        let inventory: Inventory = (1..=4).map(Block::Square).collect();
+        let token = CancelToken::new(Some(Duration::from_secs(60)), Some(1000));
+        assert!(calculate_sync_until(&Board::rectangle(4, 4), inventory, &token).complete);
+    }
+
+    #[test]
+    fn pentominoes_on_8x8_with_centre_hole_sync() {
+        assert_eq!(
+            calculate_sync(&centre_hole_board(), pentominoes())
+                .classes
+                .len(),
+            65
+        );
+    }
+
+    #[test]
+    fn pentominoes_on_8x8_with_centre_hole_parallel() {
+        assert_eq!(
+            calculate_parallel(&centre_hole_board(), pentominoes())
+                .classes
+                .len(),
+            65
+        );
+    }
+
+    #[test]
+    #[ignore = "slow, run with --release --ignored"]
+    fn pentominoes_on_6x10_sync() {
+        assert_eq!(
+            calculate_sync(&Board::rectangle(10, 6), pentominoes())
+                .classes
+                .len(),
+            2339
+        );
+    }
+
+    #[test]
+    #[ignore = "slow, run with --release --ignored"]
+    fn pentominoes_on_6x10_parallel() {
+        let result = calculate_parallel(&Board::rectangle(10, 6), pentominoes());
+        assert_eq!(result.classes.len(), 2339);
+    }
+
+    #[test]
+    #[ignore = "slow, run with --release --ignored"]
+    fn thirteen_pieces_on_8x8_sync() {
+        assert_eq!(
+            calculate_sync(&Board::rectangle(8, 8), default_inventory())
+                .classes
+                .len(),
+            16146
+        );
+    }
+
+    #[test]
+    #[ignore = "slow, run with --release --ignored"]
+    fn thirteen_pieces_on_8x8_parallel() {
+        let result = calculate_parallel(&Board::rectangle(8, 8), default_inventory());
+        assert_eq!(result.classes.len(), 16146);
     }
 }
@@ -0,0 +1,286 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::{
+    world::{World, HOLE},
+    Inventory, COLORS,
+};
+
+pub type Rgb = (u8, u8, u8);
+
+// one colour per kind of block, in `get_kind_code` order
+const KIND_COLORS: [Rgb; 13] = [
+    (241, 196, 15),
+    (149, 165, 166),
+    (41, 128, 185),
+    (230, 126, 34),
+    (231, 76, 60),
+    (46, 204, 113),
+    (155, 89, 182),
+    (26, 188, 156),
+    (236, 112, 160),
+    (52, 73, 94),
+    (211, 84, 0),
+    (127, 140, 41),
+    (120, 180, 240),
+];
+
+const PASTEL: [Rgb; 6] = [
+    (255, 179, 186),
+    (255, 223, 186),
+    (255, 255, 186),
+    (186, 255, 201),
+    (186, 225, 255),
+    (220, 198, 255),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorScheme {
+    Adjacency, // touching blocks get different colours, colours change between solutions
+    ByKind,    // every kind of block always has the same colour
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    pub scheme: ColorScheme,
+    pub palette: Option<Vec<Rgb>>, // the built in palette of the scheme when not set
+}
+
+static STYLE: OnceLock<Style> = OnceLock::new();
+
+/// Overrides the default style, has to be called before anything is drawn.
+pub fn set_style(style: Style) {
+    STYLE
+        .set(style)
+        .expect("Style was set after drawing started");
+}
+
+pub fn style() -> &'static Style {
+    STYLE.get_or_init(|| Style {
+        scheme: ColorScheme::Adjacency,
+        palette: None,
+    })
+}
+
+fn parse_hex(color: &str) -> Result<Rgb, String> {
+    let hex = color.trim().trim_start_matches('#');
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .ok_or(format!("Colour {} should look like #f1c40f", color))
+    };
+
+    if hex.len() != 6 {
+        return Err(format!("Colour {} should look like #f1c40f", color));
+    }
+    Ok((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// A named palette (default, kinds or pastel) or a comma separated list of hex colours.
+pub fn parse_palette(text: &str) -> Result<Vec<Rgb>, String> {
+    match text {
+        "default" => Ok(COLORS.to_vec()),
+        "kinds" => Ok(KIND_COLORS.to_vec()),
+        "pastel" => Ok(PASTEL.to_vec()),
+        _ => {
+            let palette = text
+                .split(',')
+                .map(parse_hex)
+                .collect::<Result<Vec<Rgb>, String>>()?;
+            if palette.is_empty() {
+                return Err("Palette has no colours".to_string());
+            }
+            Ok(palette)
+        }
+    }
+}
+
+/// Pairs of block ids that share an edge, also across joined edges, each pair once with the
+/// smaller id first.
+fn block_adjacency(world: &World) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    let rows: Vec<&[usize]> = world.rows().collect();
+
+    for (y, row) in rows.iter().enumerate() {
+        for (x, value) in row.iter().enumerate() {
+            let right = world.wrap((x as i32 + 1, y as i32)).map(|pos| &world[pos]);
+            let below = world.wrap((x as i32, y as i32 + 1)).map(|pos| &world[pos]);
+
+            for other in [right, below].into_iter().flatten() {
+                let pair = (*value.min(other), *value.max(other));
+                let is_block = |v: usize| v != 0 && v != HOLE;
+                if pair.0 != pair.1
+                    && is_block(pair.0)
+                    && is_block(pair.1)
+                    && !pairs.contains(&pair)
+                {
+                    pairs.push(pair);
+                }
+            }
+        }
+    }
+
+    pairs
+}
+
+/// Colours the blocks so touching blocks differ, picking the block with the most differently
+/// coloured neighbours first (DSatur). Only when the palette is too small colours are reused.
+fn color_graph(ids: &[usize], edges: &[(usize, usize)], n_colors: usize) -> HashMap<usize, usize> {
+    let neighbours = |id: usize| -> Vec<usize> {
+        edges
+            .iter()
+            .filter_map(|(a, b)| {
+                if *a == id {
+                    Some(*b)
+                } else if *b == id {
+                    Some(*a)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    };
+    let mut colors: HashMap<usize, usize> = HashMap::new();
+
+    while colors.len() < ids.len() {
+        let id = *ids
+            .iter()
+            .filter(|id| !colors.contains_key(id))
+            .max_by_key(|id| {
+                let mut used: Vec<usize> = neighbours(**id)
+                    .iter()
+                    .filter_map(|n| colors.get(n).copied())
+                    .collect();
+                used.sort();
+                used.dedup();
+                // most constrained first, then most neighbours, then lowest id
+                (used.len(), neighbours(**id).len(), usize::MAX - **id)
+            })
+            .unwrap();
+
+        let used: Vec<usize> = neighbours(id)
+            .iter()
+            .filter_map(|n| colors.get(n).copied())
+            .collect();
+        let color = (0..n_colors)
+            .find(|c| !used.contains(c))
+            .unwrap_or(id % n_colors);
+        colors.insert(id, color);
+    }
+
+    colors
+}
+
+/// The colour of every block on the board.
+pub fn block_colors(world: &World, inventory: &Inventory, style: &Style) -> HashMap<usize, Rgb> {
+    let mut ids: Vec<usize> = world
+        .rows()
+        .flatten()
+        .copied()
+        .filter(|v| *v != 0 && *v != HOLE)
+        .collect();
+    ids.sort();
+    ids.dedup();
+
+    colors_of_blocks(&ids, &block_adjacency(world), inventory, style)
+}
+
+/// Colours of the blocks `ids`, where `adjacency` lists the pairs of blocks that touch. Lets
+/// worlds that are not drawn in one piece, like the layers of a polycube world, keep the colour of
+/// a block the same everywhere.
+pub fn colors_of_blocks(
+    ids: &[usize],
+    adjacency: &[(usize, usize)],
+    inventory: &Inventory,
+    style: &Style,
+) -> HashMap<usize, Rgb> {
+    match style.scheme {
+        ColorScheme::Adjacency => {
+            let palette = style.palette.clone().unwrap_or(COLORS.to_vec());
+            color_graph(ids, adjacency, palette.len())
+                .into_iter()
+                .map(|(id, color)| (id, palette[color]))
+                .collect()
+        }
+        ColorScheme::ByKind => {
+            let palette = style.palette.clone().unwrap_or(KIND_COLORS.to_vec());
+            ids.iter()
+                .map(|id| {
+                    let index = match inventory.iter().find(|b| b.get_block_id() == *id) {
+                        Some(block) => block.get_kind_code() as usize,
+                        None => id - 1,
+                    };
+                    (*id, palette[index % palette.len()])
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+
+    #[test]
+    fn neighbours_never_share_a_colour() {
+        // ids 1 and 8 touch, they had the same colour when colours were picked by id
+        let world = World::parse("AAHHB\nAAHHB\nCCDDB\nCEEDB\nCFGGG\n").unwrap();
+        let style = Style {
+            scheme: ColorScheme::Adjacency,
+            palette: None,
+        };
+        let colors = block_colors(&world, &Inventory::new(), &style);
+
+        assert_eq!(colors.len(), 8);
+        for (a, b) in block_adjacency(&world) {
+            assert_ne!(colors[&a], colors[&b], "blocks {} and {}", a, b);
+        }
+    }
+
+    #[test]
+    fn four_colours_are_enough_for_a_wheel() {
+        // a block surrounded by five others that form a ring
+        let world = World::parse("BBCC\nBAAC\nFAAD\nFEED\n").unwrap();
+        let style = Style {
+            scheme: ColorScheme::Adjacency,
+            palette: Some(parse_palette("#ff0000,#00ff00,#0000ff,#ffffff").unwrap()),
+        };
+        let colors = block_colors(&world, &Inventory::new(), &style);
+
+        for (a, b) in block_adjacency(&world) {
+            assert_ne!(colors[&a], colors[&b], "blocks {} and {}", a, b);
+        }
+    }
+
+    #[test]
+    fn kinds_keep_their_colour() {
+        let style = Style {
+            scheme: ColorScheme::ByKind,
+            palette: None,
+        };
+        let first = block_colors(
+            &World::parse("AB\n").unwrap(),
+            &vec![Block::Line(1), Block::Plus(2)],
+            &style,
+        );
+        let second = block_colors(
+            &World::parse("AB\n").unwrap(),
+            &vec![Block::Plus(1), Block::Line(2)],
+            &style,
+        );
+
+        assert_eq!(first[&1], second[&2]);
+        assert_eq!(first[&2], second[&1]);
+    }
+
+    #[test]
+    fn palettes_are_parsed() {
+        assert_eq!(
+            parse_palette("#f1c40f, 2980b9").unwrap(),
+            vec![(241, 196, 15), (41, 128, 185)]
+        );
+        assert_eq!(parse_palette("pastel").unwrap().len(), 6);
+        assert!(parse_palette("#12345").is_err());
+        assert!(parse_palette("#gg0000").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use crate::{block::Block, board::Board, Inventory, Placement, Position};
+
+/// A placement together with every cell it covers, anchor included.
+#[derive(Debug, Clone)]
+pub struct TablePlacement {
+    pub placement: Placement,
+    pub cells: Vec<Position>,
+}
+
+/// Every placement that fits on the board, computed once and indexed by its first cell in
+/// row-major order, so these are exactly the placements that can cover the cursor when it is
+/// on that cell. That is the cell the block is anchored on, unless the block crosses an edge the
+/// board's topology joins.
+#[derive(Debug)]
+pub struct PlacementTable {
+    width: usize,
+    blocks: Vec<Block>,
+    placements: Vec<Vec<Vec<TablePlacement>>>, // by cell, then by index in `blocks`
+    // first cell by block index, rotation and anchor, of placements not listed under their anchor
+    listed_under: HashMap<(usize, usize, Position), Position>,
+}
+
+impl PlacementTable {
+    pub fn new(board: &Board, inventory: &Inventory) -> Self {
+        let mut placements: Vec<Vec<Vec<TablePlacement>>> =
+            vec![vec![Vec::new(); inventory.len()]; board.width() * board.height()];
+        let mut listed_under = HashMap::new();
+
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                if !board.contains((x, y)) {
+                    continue;
+                }
+
+                for (block_idx, block) in inventory.iter().enumerate() {
+                    for (rotation, geometry) in block.get_geometries().iter().enumerate() {
+                        let mut cells = vec![(x, y)];
+
+                        for point in geometry.points.iter() {
+                            let new_point = (x as i32 + point.0, y as i32 + point.1);
+                            match board.wrap(new_point) {
+                                // a block longer than a wrapped board would cover a cell twice
+                                Some(new_point) if !cells.contains(&new_point) => {
+                                    cells.push(new_point)
+                                }
+                                _ => break,
+                            }
+                        }
+
+                        if cells.len() != geometry.points.len() + 1 {
+                            continue;
+                        }
+
+                        let first = *cells.iter().min_by_key(|pos| (pos.1, pos.0)).unwrap();
+                        let listed = &mut placements[first.1 * board.width() + first.0][block_idx];
+                        // on wrapped boards different rotations or anchors can cover the same cells
+                        if listed
+                            .iter()
+                            .any(|p| cells.iter().all(|pos| p.cells.contains(pos)))
+                        {
+                            continue;
+                        }
+
+                        if first != (x, y) {
+                            listed_under.insert((block_idx, rotation, (x, y)), first);
+                        }
+                        listed.push(TablePlacement {
+                            placement: Placement {
+                                block: *block,
+                                rotation,
+                                anchor_pos: (x, y),
+                            },
+                            cells,
+                        });
+                    }
+                }
+            }
+        }
+
+        PlacementTable {
+            width: board.width(),
+            blocks: inventory.clone(),
+            placements,
+            listed_under,
+        }
+    }
+
+    /// All placements of `block` anchored on `pos`.
+    pub fn get(&self, pos: Position, block: &Block) -> &[TablePlacement] {
+        match self.blocks.iter().position(|b| b == block) {
+            Some(block_idx) => &self.placements[pos.1 * self.width + pos.0][block_idx],
+            None => &[],
+        }
+    }
+
+    /// Every placement on the board, by anchor cell in row-major order, then by block.
+    pub fn all(&self) -> impl Iterator<Item = &TablePlacement> {
+        self.placements.iter().flatten().flatten()
+    }
+
+    pub fn cells(&self, placement: &Placement) -> &[Position] {
+        let block_idx = self
+            .blocks
+            .iter()
+            .position(|b| *b == placement.block)
+            .expect("Block is not in the table");
+        let first = self
+            .listed_under
+            .get(&(block_idx, placement.rotation, placement.anchor_pos))
+            .unwrap_or(&placement.anchor_pos);
+
+        &self.placements[first.1 * self.width + first.0][block_idx]
+            .iter()
+            .find(|p| {
+                p.placement.rotation == placement.rotation
+                    && p.placement.anchor_pos == placement.anchor_pos
+            })
+            .expect("Placement does not fit on the board")
+            .cells
+    }
+}
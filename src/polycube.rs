@@ -0,0 +1,471 @@
+use std::collections::HashMap;
+
+use crate::{
+    block::Block,
+    cover::{cover, unique_solutions, CoverPlacements},
+    draw::draw_world_with_colors,
+    palette::{colors_of_blocks, style},
+    world::{World, HOLE},
+    Inventory,
+};
+
+pub type Offset3 = (i32, i32, i32);
+pub type Position3 = (usize, usize, usize);
+type Matrix = [[i32; 3]; 3];
+
+#[derive(Debug, Clone)]
+pub struct Polycube {
+    pub id: usize,
+    pub name: String,
+    pub cells: Vec<Offset3>,
+}
+
+/// The seven pieces of the Soma cube, they fill a 3x3x3 cube.
+pub fn soma_set() -> Vec<Polycube> {
+    vec![
+        Polycube::new(1, "V", vec![(0, 0, 0), (1, 0, 0), (0, 1, 0)]),
+        Polycube::new(2, "L", vec![(0, 0, 0), (1, 0, 0), (2, 0, 0), (0, 1, 0)]),
+        Polycube::new(3, "T", vec![(0, 0, 0), (1, 0, 0), (2, 0, 0), (1, 1, 0)]),
+        Polycube::new(4, "Z", vec![(0, 0, 0), (1, 0, 0), (1, 1, 0), (2, 1, 0)]),
+        Polycube::new(5, "A", vec![(0, 0, 0), (1, 0, 0), (0, 1, 0), (0, 1, 1)]),
+        Polycube::new(6, "B", vec![(0, 0, 0), (1, 0, 0), (0, 1, 0), (1, 0, 1)]),
+        Polycube::new(7, "P", vec![(0, 0, 0), (1, 0, 0), (0, 1, 0), (0, 0, 1)]),
+    ]
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut product = [[0; 3]; 3];
+    for (i, row) in product.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    product
+}
+
+fn apply(matrix: &Matrix, offset: Offset3) -> Offset3 {
+    let v = [offset.0, offset.1, offset.2];
+    let r: Vec<i32> = matrix
+        .iter()
+        .map(|row| row.iter().zip(v.iter()).map(|(a, b)| a * b).sum())
+        .collect();
+    (r[0], r[1], r[2])
+}
+
+/// The 24 rotations of a cube, or all 48 symmetries when reflections are included.
+pub fn cube_symmetries(reflections: bool) -> Vec<Matrix> {
+    let mut generators = vec![
+        [[1, 0, 0], [0, 0, -1], [0, 1, 0]], // quarter turn around x
+        [[0, -1, 0], [1, 0, 0], [0, 0, 1]], // quarter turn around z
+    ];
+    if reflections {
+        generators.push([[-1, 0, 0], [0, 1, 0], [0, 0, 1]]);
+    }
+
+    let mut group = vec![[[1, 0, 0], [0, 1, 0], [0, 0, 1]]];
+    let mut i = 0;
+    while i < group.len() {
+        for generator in generators.iter() {
+            let element = multiply(generator, &group[i]);
+            if !group.contains(&element) {
+                group.push(element);
+            }
+        }
+        i += 1;
+    }
+
+    group
+}
+
+/// Moves the cells so the first one in scan order (z, then y, then x) is the origin, and sorts
+/// them in that order. Equal shapes in the same orientation normalize to the same cells.
+fn normalize(cells: &[Offset3]) -> Vec<Offset3> {
+    let mut cells = cells.to_vec();
+    cells.sort_by_key(|c| (c.2, c.1, c.0));
+    let anchor = cells[0];
+    cells
+        .iter()
+        .map(|c| (c.0 - anchor.0, c.1 - anchor.1, c.2 - anchor.2))
+        .collect()
+}
+
+impl Polycube {
+    pub fn new(id: usize, name: &str, cells: Vec<Offset3>) -> Self {
+        Polycube {
+            id,
+            name: name.to_string(),
+            cells,
+        }
+    }
+
+    /// A flat polycube in the shape of a block.
+    pub fn from_block(block: &Block) -> Self {
+        let mut cells = vec![(0, 0, 0)];
+        cells.extend(
+            block.get_geometries()[0]
+                .points
+                .iter()
+                .map(|p| (p.0, p.1, 0)),
+        );
+
+        Polycube::new(
+            block.get_block_id(),
+            &format!("{:?}", block),
+            normalize(&cells),
+        )
+    }
+
+    /// Every distinct orientation, normalized so its anchor is at the origin.
+    pub fn orientations(&self, reflections: bool) -> Vec<Vec<Offset3>> {
+        let mut orientations: Vec<Vec<Offset3>> = Vec::new();
+
+        for matrix in cube_symmetries(reflections).iter() {
+            let rotated: Vec<Offset3> = self.cells.iter().map(|c| apply(matrix, *c)).collect();
+            let rotated = normalize(&rotated);
+            if !orientations.contains(&rotated) {
+                orientations.push(rotated);
+            }
+        }
+
+        orientations
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Board3 {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    mask: Vec<bool>, // x fastest, then y, then z
+}
+
+impl Board3 {
+    pub fn cuboid(width: usize, height: usize, depth: usize) -> Self {
+        Board3 {
+            width,
+            height,
+            depth,
+            mask: vec![true; width * height * depth],
+        }
+    }
+
+    fn index(&self, pos: Position3) -> usize {
+        pos.0 + self.width * (pos.1 + self.height * pos.2)
+    }
+
+    fn position(&self, idx: usize) -> Position3 {
+        (
+            idx % self.width,
+            idx / self.width % self.height,
+            idx / (self.width * self.height),
+        )
+    }
+
+    fn offset_index(&self, pos: Offset3) -> Option<usize> {
+        if pos.0 < 0 || pos.1 < 0 || pos.2 < 0 {
+            return None;
+        }
+
+        let pos = (pos.0 as usize, pos.1 as usize, pos.2 as usize);
+        if self.contains(pos) {
+            Some(self.index(pos))
+        } else {
+            None
+        }
+    }
+
+    pub fn contains(&self, pos: Position3) -> bool {
+        pos.0 < self.width
+            && pos.1 < self.height
+            && pos.2 < self.depth
+            && self.mask[self.index(pos)]
+    }
+
+    pub fn n_cells(&self) -> usize {
+        self.mask.iter().filter(|c| **c).count()
+    }
+
+    pub fn empty_world(&self) -> World3 {
+        World3 {
+            width: self.width,
+            height: self.height,
+            depth: self.depth,
+            cells: self
+                .mask
+                .iter()
+                .map(|c| if *c { 0 } else { HOLE })
+                .collect(),
+        }
+    }
+
+    /// The symmetries of the board as permutations of cell indices: `perm[i]` is where cell `i`
+    /// ends up.
+    pub fn symmetries(&self) -> Vec<Vec<usize>> {
+        let dims = (
+            self.width as i32 - 1,
+            self.height as i32 - 1,
+            self.depth as i32 - 1,
+        );
+        let mut symmetries = Vec::new();
+
+        for matrix in cube_symmetries(true).iter() {
+            let corner = apply(matrix, dims);
+            let shift = (corner.0.min(0), corner.1.min(0), corner.2.min(0));
+
+            let perm: Option<Vec<usize>> = (0..self.mask.len())
+                .map(|idx| {
+                    let pos = self.position(idx);
+                    let moved = apply(matrix, (pos.0 as i32, pos.1 as i32, pos.2 as i32));
+                    let moved = (moved.0 - shift.0, moved.1 - shift.1, moved.2 - shift.2);
+                    let in_box = moved.0 >= 0
+                        && moved.0 <= dims.0
+                        && moved.1 >= 0
+                        && moved.1 <= dims.1
+                        && moved.2 >= 0
+                        && moved.2 <= dims.2;
+                    if !in_box {
+                        return None;
+                    }
+
+                    let new_idx =
+                        self.index((moved.0 as usize, moved.1 as usize, moved.2 as usize));
+                    (self.mask[new_idx] == self.mask[idx]).then_some(new_idx)
+                })
+                .collect();
+
+            if let Some(perm) = perm {
+                symmetries.push(perm);
+            }
+        }
+
+        symmetries
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct World3 {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    cells: Vec<usize>, // x fastest, then y, then z
+}
+
+impl World3 {
+    pub fn layer(&self, z: usize) -> World {
+        let mut layer = World::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                layer[(x, y)] = self.cells[x + self.width * (y + self.height * z)];
+            }
+        }
+        layer
+    }
+
+    fn block_ids(&self) -> Vec<usize> {
+        let mut ids: Vec<usize> = self
+            .cells
+            .iter()
+            .copied()
+            .filter(|v| *v != 0 && *v != HOLE)
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Pairs of piece ids that share a face, in any direction, each pair once with the smaller id
+    /// first.
+    fn adjacency(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        let is_piece = |v: usize| v != 0 && v != HOLE;
+
+        for (idx, value) in self.cells.iter().enumerate() {
+            let (x, y, z) = (
+                idx % self.width,
+                idx / self.width % self.height,
+                idx / (self.width * self.height),
+            );
+            let neighbours = [
+                (x + 1 < self.width).then(|| idx + 1),
+                (y + 1 < self.height).then(|| idx + self.width),
+                (z + 1 < self.depth).then(|| idx + self.width * self.height),
+            ];
+
+            for other in neighbours.into_iter().flatten().map(|n| self.cells[n]) {
+                let pair = (*value.min(&other), *value.max(&other));
+                if pair.0 != pair.1
+                    && is_piece(pair.0)
+                    && is_piece(pair.1)
+                    && !pairs.contains(&pair)
+                {
+                    pairs.push(pair);
+                }
+            }
+        }
+
+        pairs
+    }
+}
+
+/// Pieces with the same shape, they are interchangeable.
+struct Kind {
+    ids: Vec<usize>,
+    orientations: Vec<Vec<Offset3>>,
+}
+
+fn group_kinds(pieces: &[Polycube], reflections: bool) -> Vec<Kind> {
+    let mut kinds: Vec<Kind> = Vec::new();
+
+    for piece in pieces.iter() {
+        let orientations = piece.orientations(reflections);
+        match kinds
+            .iter_mut()
+            .find(|kind| kind.orientations.contains(&orientations[0]))
+        {
+            Some(kind) => kind.ids.push(piece.id),
+            None => kinds.push(Kind {
+                ids: vec![piece.id],
+                orientations,
+            }),
+        }
+    }
+
+    kinds
+}
+
+/// Finds every way to fill the board with the pieces. With `reflections` pieces may also be
+/// mirrored, which is not possible with real cubes but is for flat pieces.
+pub fn solve3(board: &Board3, pieces: &[Polycube], reflections: bool) -> Vec<World3> {
+    let kinds = group_kinds(pieces, reflections);
+    let mut placements: CoverPlacements = vec![Vec::new(); board.mask.len()];
+
+    for (anchor, anchor_placements) in placements.iter_mut().enumerate() {
+        if !board.mask[anchor] {
+            continue;
+        }
+        let (x, y, z) = board.position(anchor);
+
+        for (kind_idx, kind) in kinds.iter().enumerate() {
+            for orientation in kind.orientations.iter() {
+                let cells: Option<Vec<usize>> = orientation
+                    .iter()
+                    .map(|c| board.offset_index((x as i32 + c.0, y as i32 + c.1, z as i32 + c.2)))
+                    .collect();
+                if let Some(cells) = cells {
+                    anchor_placements.push((kind_idx, cells));
+                }
+            }
+        }
+    }
+
+    let kind_ids: Vec<Vec<usize>> = kinds.into_iter().map(|kind| kind.ids).collect();
+    let empty_world = board.empty_world();
+
+    cover(empty_world.cells.clone(), &placements, &kind_ids)
+        .into_iter()
+        .map(|cells| World3 {
+            cells,
+            ..empty_world.clone()
+        })
+        .collect()
+}
+
+/// Removes solutions that are a rotation or reflection of another one. Identical pieces are
+/// interchangeable, and mirroring a solution is allowed when it only swaps chiral pieces.
+/// `reflections` has to match the value the solutions were found with.
+pub fn filter_out_duplicates3(
+    board: &Board3,
+    pieces: &[Polycube],
+    solutions: &[World3],
+    reflections: bool,
+) -> Vec<World3> {
+    let mut shapes = HashMap::new();
+    for (kind_idx, kind) in group_kinds(pieces, reflections).iter().enumerate() {
+        for orientation in kind.orientations.iter() {
+            shapes.insert(orientation.clone(), kind_idx);
+        }
+    }
+
+    let region_kind = |cells: &[usize]| {
+        let offsets: Vec<Offset3> = cells
+            .iter()
+            .map(|idx| {
+                let pos = board.position(*idx);
+                (pos.0 as i32, pos.1 as i32, pos.2 as i32)
+            })
+            .collect();
+        shapes.get(&normalize(&offsets)).copied()
+    };
+
+    let solution_cells: Vec<Vec<usize>> = solutions.iter().map(|s| s.cells.clone()).collect();
+    unique_solutions(&solution_cells, &board.symmetries(), region_kind)
+        .into_iter()
+        .map(|idx| solutions[idx].clone())
+        .collect()
+}
+
+/// Draws the world one horizontal layer at a time, bottom layer first. Colours are picked for the
+/// whole world, so a piece has the same colour in every layer.
+pub fn draw_world3(world: &World3) {
+    let colors = colors_of_blocks(
+        &world.block_ids(),
+        &world.adjacency(),
+        &Inventory::new(),
+        style(),
+    );
+    for z in 0..world.depth {
+        println!("Layer {}/{}", z + 1, world.depth);
+        draw_world_with_colors(&world.layer(z), &colors);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pieces_touching_between_layers_are_adjacent() {
+        // piece 1 is the bottom layer, 2 and 3 lie side by side on top of it
+        let world = World3 {
+            width: 2,
+            height: 1,
+            depth: 2,
+            cells: vec![1, 1, 2, 3],
+        };
+
+        assert_eq!(world.block_ids(), vec![1, 2, 3]);
+        assert_eq!(world.adjacency(), vec![(1, 2), (1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn cube_has_24_rotations_and_48_symmetries() {
+        assert_eq!(cube_symmetries(false).len(), 24);
+        assert_eq!(cube_symmetries(true).len(), 48);
+    }
+
+    #[test]
+    fn soma_cube_solutions() {
+        let board = Board3::cuboid(3, 3, 3);
+        let pieces = soma_set();
+
+        let solutions = solve3(&board, &pieces, false);
+        assert_eq!(solutions.len(), 11520);
+        assert_eq!(
+            filter_out_duplicates3(&board, &pieces, &solutions, false).len(),
+            240
+        );
+    }
+
+    #[test]
+    #[ignore = "slow, run with --release --ignored"]
+    fn soma_cube_solutions_with_reflections() {
+        let board = Board3::cuboid(3, 3, 3);
+        let pieces = soma_set();
+
+        let solutions = solve3(&board, &pieces, true);
+        assert_eq!(
+            filter_out_duplicates3(&board, &pieces, &solutions, true).len(),
+            563
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{
+    polycube::{filter_out_duplicates3, solve3, Board3, Polycube},
+    validators::{valid_inventory, Valid},
+    Inventory,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RectangleResult {
+    pub width: usize, // never more than `height`, the turned rectangle is the same puzzle
+    pub height: usize,
+    pub unique_solutions: usize,
+}
+
+impl RectangleResult {
+    pub fn tiles(&self) -> bool {
+        self.unique_solutions > 0
+    }
+}
+
+/// `areas[a]` tells whether some selection of the blocks covers exactly `a` cells. With
+/// `unlimited` every block can be used any number of times.
+fn achievable_areas(inventory: &Inventory, unlimited: bool, max_area: usize) -> Vec<bool> {
+    let mut areas = vec![false; max_area + 1];
+    areas[0] = true;
+
+    for block in inventory.iter() {
+        let size = block.get_size();
+        if unlimited {
+            for area in size..=max_area {
+                areas[area] = areas[area] || areas[area - size];
+            }
+        } else {
+            for area in (size..=max_area).rev() {
+                areas[area] = areas[area] || areas[area - size];
+            }
+        }
+    }
+
+    areas
+}
+
+/// The pieces to fill `area` cells with: the inventory, or with `unlimited` as many copies of
+/// every block as could fit.
+fn pieces(inventory: &Inventory, unlimited: bool, area: usize) -> Vec<Polycube> {
+    if !unlimited {
+        return inventory.iter().map(Polycube::from_block).collect();
+    }
+
+    let mut pieces: Vec<Polycube> = Vec::new();
+    for block in inventory.iter() {
+        for _ in 0..area / block.get_size() {
+            let mut piece = Polycube::from_block(block);
+            piece.id = pieces.len() + 1;
+            pieces.push(piece);
+        }
+    }
+    pieces
+}
+
+/// Tries every rectangle with an area some selection of the blocks has, up to `max_area` (all
+/// blocks together when not set). Blocks may be left over, so every selection of blocks is
+/// counted in the solutions of a rectangle. Pieces of the same kind are interchangeable and
+/// may be mirrored, as they can in the other solvers.
+pub fn tileable_rectangles(
+    inventory: &Inventory,
+    unlimited: bool,
+    max_area: Option<usize>,
+) -> Result<Vec<RectangleResult>, String> {
+    if let Valid::Invalid(reason) = valid_inventory(inventory) {
+        return Err(format!("Invalid inventory: {}", reason));
+    }
+    let total_area = inventory.iter().map(|b| b.get_size()).sum();
+    let max_area = match (max_area, unlimited) {
+        (Some(max_area), _) => max_area,
+        (None, false) => total_area,
+        (None, true) => return Err("Unlimited blocks need a maximum area".to_string()),
+    };
+
+    let areas = achievable_areas(inventory, unlimited, max_area);
+    let mut results = Vec::new();
+    for (area, achievable) in areas.iter().enumerate().skip(1) {
+        if !achievable {
+            continue;
+        }
+
+        for width in (1..=area).take_while(|w| w * w <= area) {
+            if area % width != 0 {
+                continue;
+            }
+
+            let board = Board3::cuboid(width, area / width, 1);
+            let pieces = pieces(inventory, unlimited, area);
+            let solutions = solve3(&board, &pieces, true);
+            results.push(RectangleResult {
+                width,
+                height: area / width,
+                unique_solutions: filter_out_duplicates3(&board, &pieces, &solutions, true).len(),
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+
+    fn tiling(results: &[RectangleResult]) -> Vec<(usize, usize, usize)> {
+        results
+            .iter()
+            .filter(|r| r.tiles())
+            .map(|r| (r.width, r.height, r.unique_solutions))
+            .collect()
+    }
+
+    #[test]
+    fn rectangles_of_an_inventory() {
+        let inventory = vec![Block::Square(1), Block::Line(2)];
+        let results = tileable_rectangles(&inventory, false, None).unwrap();
+
+        // areas 4, 5 and 9
+        assert_eq!(results.len(), 5);
+        assert_eq!(tiling(&results), vec![(2, 2, 1), (1, 5, 1)]);
+    }
+
+    #[test]
+    fn rectangles_with_unlimited_copies() {
+        let inventory = vec![Block::Square(1)];
+        let results = tileable_rectangles(&inventory, true, Some(16)).unwrap();
+
+        assert_eq!(
+            tiling(&results),
+            vec![(2, 2, 1), (2, 4, 1), (2, 6, 1), (2, 8, 1), (4, 4, 1)]
+        );
+        assert!(tileable_rectangles(&inventory, true, None).is_err());
+    }
+
+    #[test]
+    fn pentominoes_fill_a_3x20_rectangle_twice() {
+        let pentominoes: Inventory = [
+            Block::Line(1),
+            Block::Bridge(2),
+            Block::T(3),
+            Block::Weird(4),
+            Block::Corner(5),
+            Block::DoubleL(6),
+            Block::Baton(8),
+            Block::Cursor(9),
+            Block::Plus(10),
+            Block::Z(11),
+            Block::Stairs(12),
+            Block::L(13),
+        ]
+        .to_vec();
+        let board = Board3::cuboid(3, 20, 1);
+        let pieces = pieces(&pentominoes, false, 60);
+        let solutions = solve3(&board, &pieces, true);
+
+        assert_eq!(
+            filter_out_duplicates3(&board, &pieces, &solutions, true).len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,648 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufRead, BufReader, Read, Write},
+    iter::Peekable,
+    net::{TcpListener, TcpStream},
+    str::Chars,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    board::Board,
+    calculate_parallel_from,
+    discovery::CancelToken,
+    draw::render_svg,
+    filtering::world_id,
+    game::Game,
+    validators::{valid_inventory, Valid},
+    world::{World, HOLE},
+    Inventory,
+};
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub inventory: Inventory, // including the blocks that were already on the board
+    pub threads_done: usize,
+    pub threads: usize,
+    pub raw_solutions: usize,
+    pub solutions: Vec<World>, // unique solutions, added as the threads find them
+    pub done: bool,
+    pub complete: bool, // false when the job was cancelled before it was done
+    pub token: CancelToken,
+}
+
+/// Jobs by id. A job is removed once all of its solutions were streamed.
+#[derive(Debug, Default)]
+struct JobTable {
+    jobs: HashMap<usize, Job>,
+    last_id: usize,
+}
+
+const MAX_BODY_BYTES: usize = 1 << 20; // puzzles are a few hundred bytes
+
+type Jobs = Arc<Mutex<JobTable>>;
+
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn expect_word(chars: &mut Peekable<Chars>, word: &str, value: Json) -> Result<Json, String> {
+    for expected in word.chars() {
+        if chars.next() != Some(expected) {
+            return Err(format!("Expected {}", word));
+        }
+    }
+    Ok(value)
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    chars.next(); // opening quote
+    let mut text = String::new();
+
+    loop {
+        match chars.next().ok_or("Unterminated string")? {
+            '"' => return Ok(text),
+            '\\' => match chars.next().ok_or("Unterminated string")? {
+                'n' => text.push('\n'),
+                't' => text.push('\t'),
+                'r' => text.push('\r'),
+                'u' => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    let code = u32::from_str_radix(&code, 16).map_err(|e| e.to_string())?;
+                    text.push(char::from_u32(code).ok_or("Invalid escape")?);
+                }
+                c => text.push(c),
+            },
+            c => text.push(c),
+        }
+    }
+}
+
+fn parse_json(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    skip_whitespace(chars);
+
+    match chars.peek().copied().ok_or("Unexpected end of JSON")? {
+        '{' => {
+            chars.next();
+            let mut fields = Vec::new();
+            loop {
+                skip_whitespace(chars);
+                match chars.peek() {
+                    Some('}') => {
+                        chars.next();
+                        return Ok(Json::Object(fields));
+                    }
+                    Some(',') => {
+                        chars.next();
+                    }
+                    Some('"') => {
+                        let key = parse_string(chars)?;
+                        skip_whitespace(chars);
+                        if chars.next() != Some(':') {
+                            return Err(format!("Expected : after {}", key));
+                        }
+                        fields.push((key, parse_json(chars)?));
+                    }
+                    _ => return Err("Expected a key".to_string()),
+                }
+            }
+        }
+        '[' => {
+            chars.next();
+            let mut items = Vec::new();
+            loop {
+                skip_whitespace(chars);
+                match chars.peek() {
+                    Some(']') => {
+                        chars.next();
+                        return Ok(Json::Array(items));
+                    }
+                    Some(',') => {
+                        chars.next();
+                    }
+                    _ => items.push(parse_json(chars)?),
+                }
+            }
+        }
+        '"' => Ok(Json::String(parse_string(chars)?)),
+        't' => expect_word(chars, "true", Json::Bool(true)),
+        'f' => expect_word(chars, "false", Json::Bool(false)),
+        'n' => expect_word(chars, "null", Json::Null),
+        _ => {
+            let mut number = String::new();
+            while chars
+                .peek()
+                .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(*c))
+            {
+                number.push(chars.next().unwrap());
+            }
+            number
+                .parse()
+                .map(Json::Number)
+                .map_err(|_| format!("Unexpected JSON at '{}'", number))
+        }
+    }
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+pub fn json_string(text: &str) -> String {
+    let mut escaped = "\"".to_string();
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Reads a puzzle like `{"board": "....\n.AA.\n", "blocks": [1, 2, 3]}`. Letters on the board are
+/// blocks that are already placed, `#` are holes. Without `blocks` the default inventory is used.
+fn parse_puzzle(body: &str) -> Result<(Board, Game, Inventory), String> {
+    let json = parse_json(&mut body.chars().peekable())?;
+
+    let text = match json.get("board") {
+        Some(Json::String(text)) => text,
+        _ => return Err("Puzzle needs a board".to_string()),
+    };
+    let inventory: Inventory = match json.get("blocks") {
+        Some(Json::Array(ids)) => {
+            let ids: Vec<usize> = ids
+                .iter()
+                .map(|id| match id {
+                    Json::Number(n) if *n >= 1.0 && n.fract() == 0.0 => Ok(*n as usize),
+                    _ => Err("Blocks should be ids".to_string()),
+                })
+                .collect::<Result<_, _>>()?;
+            crate::default_inventory()
+                .into_iter()
+                .filter(|block| ids.contains(&block.get_block_id()))
+                .collect()
+        }
+        None => crate::default_inventory(),
+        _ => return Err("Blocks should be a list of ids".to_string()),
+    };
+    if let Valid::Invalid(reason) = valid_inventory(&inventory) {
+        return Err(reason);
+    }
+
+    let board = Board::parse(text)?;
+    let world = World::parse(text)?;
+    if let Some(id) = world
+        .rows()
+        .flatten()
+        .find(|v| **v != 0 && **v != HOLE && !inventory.iter().any(|b| b.get_block_id() == **v))
+    {
+        return Err(format!(
+            "Block {} is on the board but not in the inventory",
+            id
+        ));
+    }
+
+    let game = Game::from_world(&board, world, inventory.clone());
+    Ok((board, game, inventory))
+}
+
+impl Job {
+    pub fn progress_json(&self, id: usize) -> String {
+        format!(
+            "{{\"id\":{},\"status\":\"{}\",\"threads_done\":{},\"threads\":{},\"raw_solutions\":{},\"unique_solutions\":{}}}",
+            id,
+            match (self.done, self.complete) {
+                (false, _) => "running",
+                (true, true) => "done",
+                (true, false) => "cancelled",
+            },
+            self.threads_done,
+            self.threads,
+            self.raw_solutions,
+            self.solutions.len()
+        )
+    }
+}
+
+fn start_job(jobs: &Jobs, board: Board, game: Game, inventory: Inventory) -> usize {
+    let token = CancelToken::default();
+    let id = {
+        let mut table = jobs.lock().unwrap();
+        table.last_id += 1;
+        let id = table.last_id;
+        table.jobs.insert(
+            id,
+            Job {
+                inventory,
+                threads_done: 0,
+                threads: 0,
+                raw_solutions: 0,
+                solutions: Vec::new(),
+                done: false,
+                complete: false,
+                token: token.clone(),
+            },
+        );
+        id
+    };
+
+    let jobs = jobs.clone();
+    thread::spawn(move || {
+        let mut seen = HashSet::new();
+        let result = calculate_parallel_from(&board, game, &token, |found, done, total| {
+            let new: Vec<World> = found
+                .iter()
+                .filter(|world| seen.insert(world_id(&board, world)))
+                .cloned()
+                .collect();
+
+            let mut table = jobs.lock().unwrap();
+            if let Some(job) = table.jobs.get_mut(&id) {
+                job.threads_done = done;
+                job.threads = total;
+                job.raw_solutions += found.len();
+                job.solutions.extend(new);
+            }
+        });
+
+        if let Some(job) = jobs.lock().unwrap().jobs.get_mut(&id) {
+            job.complete = result.complete;
+            job.done = true;
+        }
+    });
+
+    id
+}
+
+fn bad_request(message: impl ToString) -> (&'static str, String) {
+    ("400 Bad Request", message.to_string())
+}
+
+/// Reads a request, on failure returns the status and message to answer with.
+fn read_request(stream: &mut TcpStream) -> Result<Request, (&'static str, String)> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(bad_request)?;
+    let mut parts = line.split_whitespace();
+    let method = parts
+        .next()
+        .ok_or(bad_request("Empty request"))?
+        .to_string();
+    let path = parts
+        .next()
+        .ok_or(bad_request("Request without path"))?
+        .to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(bad_request)?;
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| bad_request("Bad Content-Length"))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err((
+            "413 Payload Too Large",
+            format!("Body is larger than {} bytes", MAX_BODY_BYTES),
+        ));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(bad_request)?;
+
+    Ok(Request {
+        method,
+        path,
+        body: String::from_utf8(body).map_err(bad_request)?,
+    })
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    // the client may be gone already, there is nobody to tell about it
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn error(stream: &mut TcpStream, status: &str, message: &str) {
+    respond(
+        stream,
+        status,
+        "application/json",
+        &format!("{{\"error\":{}}}", json_string(message)),
+    );
+}
+
+/// Sends every solution of a job as a line of JSON as soon as it is found. The job is removed
+/// once the last one was sent.
+fn stream_solutions(stream: &mut TcpStream, jobs: &Jobs, id: usize) {
+    let header =
+        "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n";
+    if stream.write_all(header.as_bytes()).is_err() {
+        return;
+    }
+
+    let mut sent = 0;
+    loop {
+        let (new, done) = {
+            let mut table = jobs.lock().unwrap();
+            let Some(job) = table.jobs.get(&id) else {
+                return; // fetched by another client
+            };
+            let update = (job.solutions[sent..].to_vec(), job.done);
+            if job.done {
+                table.jobs.remove(&id);
+            }
+            update
+        };
+
+        for world in new.iter() {
+            let line = format!(
+                "{{\"index\":{},\"world\":{}}}\n",
+                sent,
+                json_string(&world.to_text())
+            );
+            if stream.write_all(line.as_bytes()).is_err() {
+                return;
+            }
+            sent += 1;
+        }
+
+        if done {
+            return;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn handle(mut stream: TcpStream, jobs: Jobs) {
+    let request = match read_request(&mut stream) {
+        Ok(request) => request,
+        Err((status, message)) => return error(&mut stream, status, &message),
+    };
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let job_id = segments
+        .get(1)
+        .and_then(|id| id.parse::<usize>().ok())
+        .filter(|id| jobs.lock().unwrap().jobs.contains_key(id));
+
+    match (request.method.as_str(), segments.as_slice(), job_id) {
+        ("POST", ["puzzles"], _) => match parse_puzzle(&request.body) {
+            Ok((board, game, inventory)) => {
+                let id = start_job(&jobs, board, game, inventory);
+                respond(
+                    &mut stream,
+                    "201 Created",
+                    "application/json",
+                    &format!("{{\"id\":{}}}", id),
+                );
+            }
+            Err(message) => error(&mut stream, "400 Bad Request", &message),
+        },
+        ("GET", ["puzzles", _], Some(id)) => {
+            let progress = jobs.lock().unwrap().jobs[&id].progress_json(id);
+            respond(&mut stream, "200 OK", "application/json", &progress);
+        }
+        ("DELETE", ["puzzles", _], Some(id)) => {
+            let progress = {
+                let table = jobs.lock().unwrap();
+                let job = &table.jobs[&id];
+                job.token.cancel();
+                job.progress_json(id)
+            };
+            respond(&mut stream, "202 Accepted", "application/json", &progress);
+        }
+        ("GET", ["puzzles", _, "solutions"], Some(id)) => stream_solutions(&mut stream, &jobs, id),
+        ("GET", ["puzzles", _, "solutions", file], Some(id)) if file.ends_with(".svg") => {
+            let svg = {
+                let table = jobs.lock().unwrap();
+                let job = &table.jobs[&id];
+                file.trim_end_matches(".svg")
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| job.solutions.get(index))
+                    .map(|world| render_svg(world, &job.inventory, 32))
+            };
+            match svg {
+                Some(svg) => respond(&mut stream, "200 OK", "image/svg+xml", &svg),
+                None => error(&mut stream, "404 Not Found", "No such solution yet"),
+            }
+        }
+        _ => error(&mut stream, "404 Not Found", "Unknown endpoint"),
+    }
+}
+
+/// Answers requests until the process ends, every connection gets its own thread.
+///
+/// - `POST /puzzles` starts solving a puzzle, see `parse_puzzle`, and returns its id
+/// - `GET /puzzles/<id>` tells how far the search is
+/// - `DELETE /puzzles/<id>` cancels the search, the solutions found so far can still be fetched
+/// - `GET /puzzles/<id>/solutions/<index>.svg` draws a solution
+/// - `GET /puzzles/<id>/solutions` streams unique solutions as JSON lines while they are found,
+///   after that the job is gone
+pub fn serve(listener: TcpListener) {
+    let jobs: Jobs = Arc::new(Mutex::new(JobTable::default()));
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        let jobs = jobs.clone();
+        thread::spawn(move || handle(stream, jobs));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    fn start_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+        addr
+    }
+
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (String, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), body.to_string())
+    }
+
+    /// Polls the progress of a job until it is no longer running.
+    fn wait_until_finished(addr: SocketAddr, id: usize) -> String {
+        loop {
+            let (status, progress) = request(addr, "GET", &format!("/puzzles/{}", id), "");
+            assert_eq!(status, "HTTP/1.1 200 OK");
+            if !progress.contains("\"status\":\"running\"") {
+                return progress;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn json_is_parsed() {
+        let json = parse_json(
+            &mut r#" {"a": [1, 2.5, -3], "b": "x\n\"y\"", "c": true, "d": null} "#
+                .chars()
+                .peekable(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            json.get("a"),
+            Some(&Json::Array(vec![
+                Json::Number(1.0),
+                Json::Number(2.5),
+                Json::Number(-3.0)
+            ]))
+        );
+        assert_eq!(json.get("b"), Some(&Json::String("x\n\"y\"".to_string())));
+        assert_eq!(json.get("c"), Some(&Json::Bool(true)));
+        assert_eq!(json.get("d"), Some(&Json::Null));
+        assert!(parse_json(&mut "{\"a\" 1}".chars().peekable()).is_err());
+    }
+
+    #[test]
+    fn solves_a_puzzle_over_http() {
+        let addr = start_server();
+        // the 12 pentominoes with the square already in the centre, 65 unique solutions
+        let puzzle = format!(
+            "{{\"board\": {}, \"blocks\": [1,2,3,4,5,6,7,8,9,10,11,12,13]}}",
+            json_string(
+                "........\n........\n........\n...GG...\n...GG...\n........\n........\n........\n"
+            )
+        );
+
+        let (status, body) = request(addr, "POST", "/puzzles", &puzzle);
+        assert_eq!(status, "HTTP/1.1 201 Created");
+        assert_eq!(body, "{\"id\":1}");
+
+        let progress = wait_until_finished(addr, 1);
+        assert!(progress.contains("\"status\":\"done\""));
+        assert!(progress.contains("\"unique_solutions\":65"));
+
+        let (status, svg) = request(addr, "GET", "/puzzles/1/solutions/64.svg", "");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<rect").count(), 64);
+
+        let (status, _) = request(addr, "GET", "/puzzles/1/solutions/65.svg", "");
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+        let (_, solutions) = request(addr, "GET", "/puzzles/1/solutions", "");
+        assert_eq!(solutions.lines().count(), 65);
+        assert!(solutions.starts_with("{\"index\":0,\"world\":\""));
+
+        // fetching the solutions removes the job
+        let (status, _) = request(addr, "GET", "/puzzles/1", "");
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+    }
+
+    #[test]
+    fn cancels_a_puzzle() {
+        let addr = start_server();
+        // all 13 blocks on the empty 8x8 board take a while
+        let puzzle = format!("{{\"board\": {}}}", json_string(&"........\n".repeat(8)));
+        request(addr, "POST", "/puzzles", &puzzle);
+
+        let (status, _) = request(addr, "DELETE", "/puzzles/1", "");
+        assert_eq!(status, "HTTP/1.1 202 Accepted");
+        assert!(wait_until_finished(addr, 1).contains("\"status\":\"cancelled\""));
+
+        let (status, _) = request(addr, "GET", "/puzzles/1/solutions", "");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let (status, _) = request(addr, "DELETE", "/puzzles/1", "");
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+    }
+
+    #[test]
+    fn rejects_bad_puzzles() {
+        let addr = start_server();
+
+        let (status, body) = request(addr, "POST", "/puzzles", "{\"blocks\": [1]}");
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+        assert_eq!(body, "{\"error\":\"Puzzle needs a board\"}");
+
+        let (status, _) = request(
+            addr,
+            "POST",
+            "/puzzles",
+            "{\"board\": \"AB\\n\", \"blocks\": [1]}",
+        );
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+
+        let (status, _) = request(addr, "GET", "/puzzles/1", "");
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+        // answered from the headers, before any of the body is read
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST /puzzles HTTP/1.1\r\nContent-Length: 100000000\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashSet;
+
+use crate::{
+    board::Board,
+    discovery::{add_possibilities_until, CancelToken, SearchStats},
+    encoding::CanonicalSolution,
+    game::Game,
+    validators::{valid_inventory, Valid},
+    Inventory,
+};
+
+#[derive(Debug, Clone)]
+pub struct SubsetResult {
+    pub blocks: Inventory,
+    pub unique_solutions: usize,
+    pub complete: bool, // false when the search was cancelled before this subset was done
+}
+
+impl SubsetResult {
+    pub fn tiles(&self) -> bool {
+        self.unique_solutions > 0
+    }
+}
+
+/// Subsets of `inventory[start..]` added to `current` with exactly `area` cells, leaving out
+/// subsets with the same kinds of blocks as one found before.
+fn collect_subsets(
+    inventory: &Inventory,
+    start: usize,
+    area: usize,
+    current: &mut Inventory,
+    subsets: &mut Vec<Inventory>,
+) {
+    if area == 0 {
+        let kinds = |blocks: &Inventory| {
+            let mut kinds: Vec<u8> = blocks.iter().map(|b| b.get_kind_code()).collect();
+            kinds.sort();
+            kinds
+        };
+        if !subsets.iter().any(|s| kinds(s) == kinds(current)) {
+            subsets.push(current.clone());
+        }
+        return;
+    }
+
+    for idx in start..inventory.len() {
+        let block = inventory[idx];
+        if block.get_size() > area {
+            continue;
+        }
+
+        current.push(block);
+        collect_subsets(
+            inventory,
+            idx + 1,
+            area - block.get_size(),
+            current,
+            subsets,
+        );
+        current.pop();
+    }
+}
+
+/// Every subset of the inventory whose blocks have the same area as the board, with the number
+/// of unique solutions it has on it. Blocks of the same kind are interchangeable, so swapping them
+/// gives no new solution. The limits of `token` apply to every subset on its own.
+pub fn tiling_subsets(
+    board: &Board,
+    inventory: &Inventory,
+    token: &CancelToken,
+) -> Result<Vec<SubsetResult>, String> {
+    if let Valid::Invalid(reason) = valid_inventory(inventory) {
+        return Err(format!("Invalid inventory: {}", reason));
+    }
+
+    let mut subsets = Vec::new();
+    collect_subsets(inventory, 0, board.n_cells(), &mut Vec::new(), &mut subsets);
+
+    Ok(subsets
+        .into_iter()
+        .map(|blocks| {
+            let mut game = Game::new(board, blocks.clone());
+            let mut solved_worlds = Vec::new();
+            let token = token.for_next_search();
+            add_possibilities_until(
+                &mut game,
+                &mut solved_worlds,
+                &mut SearchStats::default(),
+                &token,
+            );
+
+            let unique: HashSet<CanonicalSolution> = solved_worlds
+                .iter()
+                .map(|world| CanonicalSolution::new(board, world, &blocks))
+                .collect();
+            SubsetResult {
+                blocks,
+                unique_solutions: unique.len(),
+                complete: !token.stopped_early(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+
+    #[test]
+    fn subsets_have_the_area_of_the_board() {
+        let inventory = vec![
+            Block::Corner(1),
+            Block::Cursor(2),
+            Block::L(3),
+            Block::Bridge(4),
+            Block::Baton(5),
+            Block::Square(6),
+        ];
+        let results =
+            tiling_subsets(&Board::rectangle(5, 3), &inventory, &CancelToken::default()).unwrap();
+        assert_eq!(results.len(), 10);
+
+        let tiling: Vec<String> = results
+            .iter()
+            .filter(|r| r.tiles())
+            .map(|r| r.blocks.iter().map(|b| b.get_letter()).collect())
+            .collect();
+        assert_eq!(tiling, vec!["VPL", "VPU", "PUY"]);
+        assert!(results.iter().all(|r| r.complete));
+
+        // the limit stops every subset on its own
+        let token = CancelToken::new(None, Some(1));
+        let results = tiling_subsets(&Board::rectangle(5, 3), &inventory, &token).unwrap();
+        assert_eq!(results.iter().filter(|r| r.tiles()).count(), 3);
+    }
+
+    #[test]
+    fn blocks_of_the_same_kind_are_one_subset() {
+        let inventory: Inventory = (1..=6).map(Block::Square).collect();
+        let results =
+            tiling_subsets(&Board::rectangle(4, 4), &inventory, &CancelToken::default()).unwrap();
+
+        assert_eq!(results.len(), 1);
+        // the squares can only be put in a grid
+        assert_eq!(results[0].unique_solutions, 1);
+        assert!(tiling_subsets(
+            &Board::rectangle(4, 4),
+            &vec![Block::Square(1), Block::Square(1)],
+            &CancelToken::default()
+        )
+        .is_err());
+    }
+}
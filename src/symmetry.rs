@@ -0,0 +1,188 @@
+use crate::{
+    board::Board,
+    world::{Topology, World},
+    Position,
+};
+
+/// An element of the symmetry group of a square.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    MirrorHorizontal, // flips left and right
+    MirrorVertical,   // flips top and bottom
+    Diagonal,         // transpose over the top-left to bottom-right diagonal
+    AntiDiagonal,     // transpose over the top-right to bottom-left diagonal
+}
+
+pub const SYMMETRIES: [Symmetry; 8] = [
+    Symmetry::Identity,
+    Symmetry::Rotate90,
+    Symmetry::Rotate180,
+    Symmetry::Rotate270,
+    Symmetry::MirrorHorizontal,
+    Symmetry::MirrorVertical,
+    Symmetry::Diagonal,
+    Symmetry::AntiDiagonal,
+];
+
+impl Symmetry {
+    /// Whether the transform turns a `width` x `height` area into a `height` x `width` one.
+    pub fn swaps_axes(&self) -> bool {
+        matches!(
+            self,
+            Self::Rotate90 | Self::Rotate270 | Self::Diagonal | Self::AntiDiagonal
+        )
+    }
+
+    /// The position in the original `width` x `height` area that ends up at `pos` after the
+    /// transform.
+    pub fn source_position(&self, pos: Position, width: usize, height: usize) -> Position {
+        let (x, y) = pos;
+        match self {
+            Self::Identity => (x, y),
+            Self::Rotate90 => (y, height - 1 - x),
+            Self::Rotate180 => (width - 1 - x, height - 1 - y),
+            Self::Rotate270 => (width - 1 - y, x),
+            Self::MirrorHorizontal => (width - 1 - x, y),
+            Self::MirrorVertical => (x, height - 1 - y),
+            Self::Diagonal => (y, x),
+            Self::AntiDiagonal => (width - 1 - y, height - 1 - x),
+        }
+    }
+}
+
+/// The symmetries that map the board, including its holes, onto itself.
+pub fn symmetry_group(board: &Board) -> Vec<Symmetry> {
+    let (width, height) = (board.width(), board.height());
+
+    SYMMETRIES
+        .iter()
+        // a cylinder only wraps around left and right, turning it would make it wrap top and bottom
+        .filter(|symmetry| {
+            !symmetry.swaps_axes() || (width == height && board.topology() != Topology::Cylinder)
+        })
+        .filter(|symmetry| {
+            (0..height).all(|y| {
+                (0..width).all(|x| {
+                    board.contains((x, y))
+                        == board.contains(symmetry.source_position((x, y), width, height))
+                })
+            })
+        })
+        .copied()
+        .collect()
+}
+
+/// Applies a symmetry to a world. Symmetries that swap the axes turn a `width` x `height` world
+/// into a `height` x `width` one.
+pub fn transform_world(world: &World, symmetry: Symmetry) -> World {
+    let (width, height) = (world.width(), world.height());
+    let mut transformed = match symmetry.swaps_axes() {
+        true => World::new(height, width),
+        false => World::new(width, height),
+    }
+    .with_topology(world.topology());
+
+    for y in 0..transformed.height() {
+        for x in 0..transformed.width() {
+            transformed[(x, y)] = world[symmetry.source_position((x, y), width, height)];
+        }
+    }
+
+    transformed
+}
+
+/// A symmetry of the board followed by a shift, shifts are only possible along the edges that
+/// wrap around.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub symmetry: Symmetry,
+    pub shift: Position, // how far cells move right and down, wrapping around
+}
+
+/// Shifts that map the board, including its holes, onto itself. Only (0, 0) on flat boards.
+fn shift_group(board: &Board) -> Vec<Position> {
+    let (width, height) = (board.width(), board.height());
+    let x_shifts = if board.topology().wraps_x() { width } else { 1 };
+    let y_shifts = if board.topology().wraps_y() {
+        height
+    } else {
+        1
+    };
+
+    (0..y_shifts)
+        .flat_map(|dy| (0..x_shifts).map(move |dx| (dx, dy)))
+        .filter(|(dx, dy)| {
+            (0..height).all(|y| {
+                (0..width).all(|x| {
+                    board.contains((x, y)) == board.contains(((x + dx) % width, (y + dy) % height))
+                })
+            })
+        })
+        .collect()
+}
+
+/// Every symmetry of the board combined with every shift of it.
+pub fn transform_group(board: &Board) -> Vec<Transform> {
+    let shifts = shift_group(board);
+
+    symmetry_group(board)
+        .into_iter()
+        .flat_map(|symmetry| {
+            shifts.iter().map(move |shift| Transform {
+                symmetry,
+                shift: *shift,
+            })
+        })
+        .collect()
+}
+
+pub fn apply_transform(world: &World, transform: Transform) -> World {
+    let transformed = transform_world(world, transform.symmetry);
+    if transform.shift == (0, 0) {
+        return transformed;
+    }
+
+    let (width, height) = (world.width(), world.height());
+    let mut shifted = transformed.clone();
+    for y in 0..height {
+        for x in 0..width {
+            shifted[(
+                (x + transform.shift.0) % width,
+                (y + transform.shift.1) % height,
+            )] = transformed[(x, y)];
+        }
+    }
+
+    shifted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turning_swaps_width_and_height() {
+        let world = World::parse("ABC\nDEF\n").unwrap();
+
+        assert_eq!(
+            transform_world(&world, Symmetry::Diagonal).to_text(),
+            "AD\nBE\nCF\n"
+        );
+        assert_eq!(
+            transform_world(&world, Symmetry::Rotate90).to_text(),
+            "DA\nEB\nFC\n"
+        );
+        assert_eq!(
+            transform_world(&world, Symmetry::AntiDiagonal).to_text(),
+            "FC\nEB\nDA\n"
+        );
+        assert_eq!(
+            transform_world(&world, Symmetry::Rotate180).to_text(),
+            "FED\nCBA\n"
+        );
+    }
+}
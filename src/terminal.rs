@@ -0,0 +1,282 @@
+use std::{env, sync::OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Glyphs {
+    Legacy,     // eighth block lines from "Symbols for Legacy Computing"
+    BoxDrawing, // ┌─┐ lines, in almost every font
+    Ascii,      // +-| only
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Terminal {
+    pub glyphs: Glyphs,
+    pub colors: ColorDepth,
+}
+
+/// Characters for the borders of a cell that touch another block or the edge of the board.
+pub struct BorderGlyphs {
+    pub left: &'static str,
+    pub right: &'static str,
+    pub top: &'static str,
+    pub bottom: &'static str,
+    pub top_left: &'static str,
+    pub top_right: &'static str,
+    pub bottom_left: &'static str,
+    pub bottom_right: &'static str,
+    pub seam_vertical: &'static str, // where a block crosses a joined edge
+    pub seam_horizontal: &'static str,
+}
+
+static TERMINAL: OnceLock<Terminal> = OnceLock::new();
+
+fn env_var(name: &str) -> String {
+    env::var(name).unwrap_or_default()
+}
+
+/// Guesses what the terminal can show from the usual environment variables.
+pub fn detect_terminal() -> Terminal {
+    let term = env_var("TERM");
+    let colorterm = env_var("COLORTERM");
+    let locale = [env_var("LC_ALL"), env_var("LC_CTYPE"), env_var("LANG")].join(" ");
+    let unicode = locale.to_uppercase().contains("UTF-8") || locale.to_uppercase().contains("UTF8");
+
+    let colors = if env::var_os("NO_COLOR").is_some() || term.is_empty() || term == "dumb" {
+        ColorDepth::None
+    } else if colorterm == "truecolor" || colorterm == "24bit" {
+        ColorDepth::TrueColor
+    } else if term.contains("256color") {
+        ColorDepth::Ansi256
+    } else {
+        ColorDepth::Ansi16
+    };
+
+    let glyphs = if !unicode || term == "dumb" {
+        Glyphs::Ascii
+    } else if colors == ColorDepth::TrueColor {
+        Glyphs::Legacy
+    } else {
+        Glyphs::BoxDrawing
+    };
+
+    Terminal { glyphs, colors }
+}
+
+/// Width of the terminal in characters as it reports it, `COLUMNS` when stdout is not a terminal
+/// and 80 when that is not set either.
+pub fn terminal_columns() -> usize {
+    window_columns()
+        .or_else(|| env::var("COLUMNS").ok().and_then(|c| c.parse().ok()))
+        .unwrap_or(80)
+}
+
+#[cfg(unix)]
+fn window_columns() -> Option<usize> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    // only writes to `size`, fails when stdout is not a terminal
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+
+    (result == 0 && size.ws_col > 0).then_some(size.ws_col as usize)
+}
+
+#[cfg(not(unix))]
+fn window_columns() -> Option<usize> {
+    None
+}
+
+/// Overrides the detected terminal, has to be called before anything is drawn.
+pub fn set_terminal(terminal: Terminal) {
+    TERMINAL
+        .set(terminal)
+        .expect("Terminal was set after drawing started");
+}
+
+pub fn terminal() -> Terminal {
+    *TERMINAL.get_or_init(detect_terminal)
+}
+
+impl Glyphs {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "legacy" => Ok(Glyphs::Legacy),
+            "box" => Ok(Glyphs::BoxDrawing),
+            "ascii" => Ok(Glyphs::Ascii),
+            _ => Err(format!("Unknown glyphs {}, use legacy, box or ascii", name)),
+        }
+    }
+
+    pub fn borders(&self) -> BorderGlyphs {
+        match self {
+            Glyphs::Legacy => BorderGlyphs {
+                left: "🭰",
+                right: "🭵",
+                top: "🭶",
+                bottom: "🭻",
+                top_left: "🭽",
+                top_right: "🭾",
+                bottom_left: "🭼",
+                bottom_right: "🭿",
+                seam_vertical: "┆",
+                seam_horizontal: "┄",
+            },
+            Glyphs::BoxDrawing => BorderGlyphs {
+                left: "│",
+                right: "│",
+                top: "─",
+                bottom: "─",
+                top_left: "┌",
+                top_right: "┐",
+                bottom_left: "└",
+                bottom_right: "┘",
+                seam_vertical: "┆",
+                seam_horizontal: "┄",
+            },
+            Glyphs::Ascii => BorderGlyphs {
+                left: "|",
+                right: "|",
+                top: "-",
+                bottom: "-",
+                top_left: "+",
+                top_right: "+",
+                bottom_left: "+",
+                bottom_right: "+",
+                seam_vertical: ":",
+                seam_horizontal: ".",
+            },
+        }
+    }
+}
+
+impl ColorDepth {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "truecolor" => Ok(ColorDepth::TrueColor),
+            "256" => Ok(ColorDepth::Ansi256),
+            "16" => Ok(ColorDepth::Ansi16),
+            "none" => Ok(ColorDepth::None),
+            _ => Err(format!(
+                "Unknown colours {}, use truecolor, 256, 16 or none",
+                name
+            )),
+        }
+    }
+}
+
+// the 16 standard colours as xterm shows them, in SGR order
+const ANSI16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> i32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2);
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Index of the closest colour in the 6x6x6 cube of the 256 colour palette.
+pub fn ansi256(color: (u8, u8, u8)) -> u8 {
+    let level = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+    16 + 36 * level(color.0) + 6 * level(color.1) + level(color.2)
+}
+
+/// Index of the closest of the 16 standard colours.
+pub fn ansi16(color: (u8, u8, u8)) -> u8 {
+    (0..16)
+        .min_by_key(|i| distance(ANSI16[*i as usize], color))
+        .unwrap()
+}
+
+/// SGR parameters selecting `color` as background, or as foreground when `background` is false.
+fn color_code(color: (u8, u8, u8), depth: ColorDepth, background: bool) -> Option<String> {
+    let offset = if background { 10 } else { 0 };
+    match depth {
+        ColorDepth::TrueColor => Some(format!(
+            "{};2;{};{};{}",
+            38 + offset,
+            color.0,
+            color.1,
+            color.2
+        )),
+        ColorDepth::Ansi256 => Some(format!("{};5;{}", 38 + offset, ansi256(color))),
+        ColorDepth::Ansi16 => match ansi16(color) {
+            i @ 0..=7 => Some(format!("{}", 30 + offset + i)),
+            i => Some(format!("{}", 90 + offset + i - 8)),
+        },
+        ColorDepth::None => None,
+    }
+}
+
+/// Black text on a background of `color`, as well as the terminal can show it.
+pub fn paint(text: &str, color: (u8, u8, u8), depth: ColorDepth) -> String {
+    match color_code(color, depth, true) {
+        Some(background) => format!("\x1b[{};30m{}\x1b[0m", background, text),
+        None => text.to_string(),
+    }
+}
+
+/// Text in `color` on the terminal's own background.
+pub fn paint_text(text: &str, color: (u8, u8, u8), depth: ColorDepth) -> String {
+    match color_code(color, depth, false) {
+        Some(foreground) => format!("\x1b[{}m{}\x1b[0m", foreground, text),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colours_map_to_the_closest_palette_entry() {
+        assert_eq!(ansi256((0, 0, 0)), 16);
+        assert_eq!(ansi256((255, 255, 255)), 231);
+        assert_eq!(ansi256((255, 0, 0)), 196);
+        assert_eq!(ansi16((250, 10, 10)), 9);
+        assert_eq!(ansi16((10, 10, 200)), 4);
+    }
+
+    #[test]
+    fn paint_uses_the_colour_depth() {
+        let color = (255, 0, 0);
+
+        assert_eq!(paint("x", color, ColorDepth::None), "x");
+        assert_eq!(
+            paint("x", color, ColorDepth::Ansi16),
+            "\x1b[101;30mx\x1b[0m"
+        );
+        assert_eq!(
+            paint("x", color, ColorDepth::Ansi256),
+            "\x1b[48;5;196;30mx\x1b[0m"
+        );
+        assert_eq!(
+            paint("x", color, ColorDepth::TrueColor),
+            "\x1b[48;2;255;0;0;30mx\x1b[0m"
+        );
+        assert_eq!(
+            paint_text("x", color, ColorDepth::Ansi16),
+            "\x1b[91mx\x1b[0m"
+        );
+        assert_eq!(paint_text("x", color, ColorDepth::None), "x");
+    }
+}
@@ -1,6 +1,12 @@
 use std::collections::HashSet;
 
-use crate::{Inventory, WORLD_HEIGHT, WORLD_WIDTH};
+use crate::{
+    block::{Block, BlockGeometry},
+    lattice::{LatticeBoard, Polyform},
+    polycube::{Board3, Polycube},
+    world::World,
+    Inventory, Offset,
+};
 
 #[derive(Debug, PartialEq)]
 pub enum Valid {
@@ -25,10 +31,319 @@ pub fn valid_inventory(inventory: &Inventory) -> Valid {
     Valid::Valid
 }
 
-pub fn valid_pos(pos: (i32, i32)) -> Valid {
-    if 0 <= pos.0 && pos.0 < WORLD_WIDTH as i32 && 0 <= pos.1 && pos.1 < WORLD_HEIGHT as i32 {
+pub fn valid_pos(world: &World, pos: (i32, i32)) -> Valid {
+    if 0 <= pos.0 && pos.0 < world.width() as i32 && 0 <= pos.1 && pos.1 < world.height() as i32 {
         Valid::Valid
     } else {
         Valid::Invalid("Not in range".to_string())
     }
 }
+
+fn connected<T: Copy + PartialEq>(cells: &[T], neighbours: impl Fn(T) -> Vec<T>) -> bool {
+    let mut reached = vec![cells[0]];
+    let mut i = 0;
+
+    while i < reached.len() {
+        for neighbour in neighbours(reached[i]) {
+            if cells.contains(&neighbour) && !reached.contains(&neighbour) {
+                reached.push(neighbour);
+            }
+        }
+        i += 1;
+    }
+
+    reached.len() == cells.len()
+}
+
+pub fn valid_polycubes(board: &Board3, pieces: &[Polycube]) -> Valid {
+    let mut unique_ids: HashSet<usize> = HashSet::new();
+
+    for piece in pieces.iter() {
+        if piece.id == 0 {
+            return Valid::Invalid("Id 0 not allowed".to_string());
+        }
+        if !unique_ids.insert(piece.id) {
+            return Valid::Invalid(format!("Duplicate id {} found", piece.id));
+        }
+        if piece.cells.is_empty() {
+            return Valid::Invalid(format!("Piece {} has no cells", piece.name));
+        }
+        if piece.cells.iter().collect::<HashSet<_>>().len() != piece.cells.len() {
+            return Valid::Invalid(format!("Piece {} has overlapping cells", piece.name));
+        }
+        let neighbours = |(x, y, z): (i32, i32, i32)| {
+            vec![
+                (x + 1, y, z),
+                (x - 1, y, z),
+                (x, y + 1, z),
+                (x, y - 1, z),
+                (x, y, z + 1),
+                (x, y, z - 1),
+            ]
+        };
+        if !connected(&piece.cells, neighbours) {
+            return Valid::Invalid(format!("Piece {} is not connected", piece.name));
+        }
+    }
+
+    let volume: usize = pieces.iter().map(|piece| piece.cells.len()).sum();
+    if volume != board.n_cells() {
+        return Valid::Invalid(format!(
+            "Pieces fill {} cells but the board has {}",
+            volume,
+            board.n_cells()
+        ));
+    }
+
+    Valid::Valid
+}
+
+pub fn valid_polyforms(board: &LatticeBoard, pieces: &[Polyform]) -> Valid {
+    let mut unique_ids: HashSet<usize> = HashSet::new();
+
+    for piece in pieces.iter() {
+        if piece.id == 0 {
+            return Valid::Invalid("Id 0 not allowed".to_string());
+        }
+        if !unique_ids.insert(piece.id) {
+            return Valid::Invalid(format!("Duplicate id {} found", piece.id));
+        }
+        if piece.cells.is_empty() {
+            return Valid::Invalid(format!("Piece {} has no cells", piece.name));
+        }
+        if !connected(&piece.cells, |cell| board.lattice.neighbours(cell)) {
+            return Valid::Invalid(format!("Piece {} is not connected", piece.name));
+        }
+    }
+
+    let area: usize = pieces.iter().map(|piece| piece.cells.len()).sum();
+    if area != board.cells().len() {
+        return Valid::Invalid(format!(
+            "Pieces fill {} cells but the board has {}",
+            area,
+            board.cells().len()
+        ));
+    }
+
+    Valid::Valid
+}
+
+/// Cells a block covers in row major order, moved so the first one is the anchor.
+fn normalize_cells(cells: &[Offset]) -> Vec<Offset> {
+    let mut cells = cells.to_vec();
+    cells.sort_by_key(|c| (c.1, c.0));
+    let anchor = cells[0];
+    cells
+        .iter()
+        .map(|c| (c.0 - anchor.0, c.1 - anchor.1))
+        .collect()
+}
+
+/// The rotations and mirror images of a shape.
+fn shape_orientations(cells: &[Offset]) -> Vec<Vec<Offset>> {
+    let transforms: [fn(Offset) -> Offset; 8] = [
+        |(x, y)| (x, y),
+        |(x, y)| (-y, x),
+        |(x, y)| (-x, -y),
+        |(x, y)| (y, -x),
+        |(x, y)| (-x, y),
+        |(x, y)| (x, -y),
+        |(x, y)| (y, x),
+        |(x, y)| (-y, -x),
+    ];
+
+    let mut orientations: Vec<Vec<Offset>> = Vec::new();
+    for transform in transforms.iter() {
+        let moved: Vec<Offset> = cells.iter().map(|c| transform(*c)).collect();
+        let moved = normalize_cells(&moved);
+        if !orientations.contains(&moved) {
+            orientations.push(moved);
+        }
+    }
+
+    orientations
+}
+
+fn expected_size(block: &Block) -> usize {
+    match block {
+        Block::Square(_) => 4,
+        _ => 5,
+    }
+}
+
+/// Checks that the orientations of a block are exactly the distinct rotations and mirror images
+/// of one connected shape, each with its anchor on the top left most cell. Returns every problem
+/// found, none when the block is valid.
+pub fn geometry_problems(block: &Block) -> Vec<String> {
+    geometry_set_problems(
+        &format!("{:?}", block),
+        expected_size(block),
+        &block.get_geometries(),
+    )
+}
+
+fn geometry_set_problems(name: &str, size: usize, geometries: &[BlockGeometry]) -> Vec<String> {
+    if geometries.is_empty() {
+        return vec![format!("{} has no orientations", name)];
+    }
+
+    let mut problems = Vec::new();
+    let mut shapes: Vec<(usize, Vec<Offset>)> = Vec::new(); // orientations with a proper shape
+
+    for (rotation, geometry) in geometries.iter().enumerate() {
+        let mut cells = vec![(0, 0)];
+        cells.extend(geometry.points.iter());
+        let n_problems = problems.len();
+
+        if cells.len() != size {
+            problems.push(format!(
+                "{} orientation {} has {} cells instead of {}",
+                name,
+                rotation,
+                cells.len(),
+                size
+            ));
+        }
+        if cells.iter().collect::<HashSet<_>>().len() != cells.len() {
+            problems.push(format!(
+                "{} orientation {} has overlapping cells",
+                name, rotation
+            ));
+        }
+        if let Some(point) = geometry
+            .points
+            .iter()
+            .find(|p| p.1 < 0 || (p.1 == 0 && p.0 < 0))
+        {
+            problems.push(format!(
+                "{} orientation {} has cell {:?} before its anchor",
+                name, rotation, point
+            ));
+        }
+        let neighbours = |(x, y): Offset| vec![(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)];
+        if !connected(&cells, neighbours) {
+            problems.push(format!(
+                "{} orientation {} is not connected",
+                name, rotation
+            ));
+        }
+        if problems.len() > n_problems {
+            continue;
+        }
+
+        let shape = normalize_cells(&cells);
+        match shapes.iter().find(|(_, s)| *s == shape) {
+            Some((other, _)) => problems.push(format!(
+                "{} orientation {} duplicates orientation {}",
+                name, rotation, other
+            )),
+            None => shapes.push((rotation, shape)),
+        }
+    }
+
+    let Some((first, first_shape)) = shapes.first() else {
+        return problems;
+    };
+    let expected = shape_orientations(first_shape);
+    for (rotation, shape) in shapes.iter() {
+        if !expected.contains(shape) {
+            problems.push(format!(
+                "{} orientation {} is not a rotation or mirror image of orientation {}",
+                name, rotation, first
+            ));
+        }
+    }
+    if geometries.len() != expected.len() {
+        problems.push(format!(
+            "{} has {} orientations but its shape has {}",
+            name,
+            geometries.len(),
+            expected.len()
+        ));
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geometry(points: &[Offset]) -> BlockGeometry {
+        BlockGeometry {
+            points: points.to_vec(),
+        }
+    }
+
+    #[test]
+    fn all_block_geometries_are_valid() {
+        for block in crate::default_inventory() {
+            assert_eq!(
+                geometry_problems(&block),
+                Vec::<String>::new(),
+                "{:?}",
+                block
+            );
+        }
+    }
+
+    #[test]
+    fn broken_geometries_are_rejected() {
+        let line = [(1, 0), (2, 0), (3, 0), (4, 0)];
+        let standing_line = [(0, 1), (0, 2), (0, 3), (0, 4)];
+
+        assert_eq!(
+            geometry_set_problems("Line", 5, &[geometry(&line), geometry(&standing_line)]),
+            Vec::<String>::new()
+        );
+        // missing an orientation
+        assert_ne!(
+            geometry_set_problems("Line", 5, &[geometry(&line)]),
+            Vec::<String>::new()
+        );
+        // same orientation twice
+        assert_ne!(
+            geometry_set_problems("Line", 5, &[geometry(&line), geometry(&line)]),
+            Vec::<String>::new()
+        );
+        // wrong cell count
+        assert_ne!(
+            geometry_set_problems("Line", 4, &[geometry(&line)]),
+            Vec::<String>::new()
+        );
+        // gap in the line
+        assert_ne!(
+            geometry_set_problems("Line", 5, &[geometry(&[(1, 0), (2, 0), (3, 0), (5, 0)])]),
+            Vec::<String>::new()
+        );
+        // anchor is not the top left most cell
+        assert_ne!(
+            geometry_set_problems("Line", 5, &[geometry(&[(-1, 0), (1, 0), (2, 0), (3, 0)])]),
+            Vec::<String>::new()
+        );
+        // a different shape
+        assert_ne!(
+            geometry_set_problems(
+                "Line",
+                5,
+                &[geometry(&line), geometry(&[(0, 1), (0, 2), (0, 3), (1, 3)])]
+            ),
+            Vec::<String>::new()
+        );
+        // every broken orientation is reported
+        assert_eq!(
+            geometry_set_problems(
+                "Line",
+                5,
+                &[
+                    geometry(&[(1, 0), (2, 0), (3, 0)]),
+                    geometry(&[(0, 1), (0, 2), (0, 3), (2, 3)])
+                ]
+            ),
+            vec![
+                "Line orientation 0 has 4 cells instead of 5",
+                "Line orientation 1 is not connected",
+            ]
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{
+    analytics::rotation_of,
+    block::Block,
+    board::Board,
+    encoding::CanonicalSolution,
+    filtering::world_id,
+    world::{id_char, World, HOLE},
+    Inventory, Position,
+};
+
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub label: usize,         // value the cells have in the transcribed grid
+    pub cells: Vec<Position>, // row major
+}
+
+#[derive(Debug, Clone)]
+pub struct Verified {
+    pub world: World, // the grid with every region labeled with the id of its block
+    pub id: String,
+    pub code: String,
+}
+
+/// Splits a world into connected areas of equal value, in order of their first cell.
+pub fn regions(world: &World) -> Vec<Region> {
+    let mut seen = vec![vec![false; world.width()]; world.height()];
+    let mut regions = Vec::new();
+
+    for y in 0..world.height() {
+        for x in 0..world.width() {
+            let label = world[(x, y)];
+            if seen[y][x] || label == 0 || label == HOLE {
+                continue;
+            }
+
+            seen[y][x] = true;
+            let mut cells = vec![(x, y)];
+            let mut i = 0;
+            while i < cells.len() {
+                let (cx, cy) = cells[i];
+                let neighbours = [
+                    (cx.wrapping_sub(1), cy),
+                    (cx + 1, cy),
+                    (cx, cy.wrapping_sub(1)),
+                    (cx, cy + 1),
+                ];
+                for (nx, ny) in neighbours {
+                    if nx < world.width()
+                        && ny < world.height()
+                        && !seen[ny][nx]
+                        && world[(nx, ny)] == label
+                    {
+                        seen[ny][nx] = true;
+                        cells.push((nx, ny));
+                    }
+                }
+                i += 1;
+            }
+
+            cells.sort_by_key(|c| (c.1, c.0));
+            regions.push(Region { label, cells });
+        }
+    }
+
+    regions
+}
+
+/// Checks a transcribed solution and labels its regions with the blocks of the inventory. The
+/// letters of the grid don't have to match the block ids, every region just has to have the shape
+/// of an unused block. All problems are reported, not only the first one.
+pub fn verify(text: &str, inventory: &Inventory) -> Result<Verified, Vec<String>> {
+    let board = Board::parse(text).map_err(|e| vec![e])?;
+    let grid = World::parse(text).map_err(|e| vec![e])?;
+
+    let mut errors = Vec::new();
+    let mut world = board.empty_world();
+    let mut unused: Vec<Block> = inventory.clone();
+
+    for region in regions(&grid) {
+        let anchor = region.cells[0];
+        let position = unused
+            .iter()
+            .position(|block| rotation_of(block, &region.cells).is_some());
+
+        match position {
+            Some(idx) => {
+                let block = unused.remove(idx);
+                for pos in region.cells.iter() {
+                    world[*pos] = block.get_block_id();
+                }
+            }
+            None => {
+                let shape = inventory
+                    .iter()
+                    .find(|block| rotation_of(block, &region.cells).is_some());
+                errors.push(match shape {
+                    Some(block) => format!(
+                        "Region '{}' at {:?} is a {:?}, but all of those are used already",
+                        id_char(region.label),
+                        anchor,
+                        block
+                    ),
+                    None => format!(
+                        "Region '{}' at {:?} with {} cells is not the shape of any block",
+                        id_char(region.label),
+                        anchor,
+                        region.cells.len()
+                    ),
+                });
+            }
+        }
+    }
+
+    for (y, row) in grid.rows().enumerate() {
+        for (x, value) in row.iter().enumerate() {
+            if *value == 0 {
+                errors.push(format!("Cell {:?} is not covered", (x, y)));
+            }
+        }
+    }
+
+    for block in unused.iter() {
+        errors.push(format!("{:?} is not used", block));
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let code = CanonicalSolution::new(&board, &world, inventory)
+        .to_base64()
+        .map_err(|error| vec![error])?;
+    Ok(Verified {
+        id: world_id(&board, &world),
+        code,
+        world,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pentominoes() -> Inventory {
+        crate::default_inventory()
+            .into_iter()
+            .filter(|block| block.get_size() == 5)
+            .collect()
+    }
+
+    // letters are the usual pentomino names, not block ids
+    const SOLUTION: &str = "\
+LUUUVVVFYI
+LUXUVFFFYI
+LXXXVZFYYI
+LLXZZZTWYI
+PPPZNNTWWI
+PPNNNTTTWW
+";
+
+    #[test]
+    fn accepts_a_transcribed_solution() {
+        let verified = verify(SOLUTION, &pentominoes()).unwrap();
+
+        assert_eq!(verified.world[(0, 0)], 13); // the L block
+        assert!(verified.world.rows().flatten().all(|v| *v != 0));
+    }
+
+    #[test]
+    fn identifies_mirrored_transcriptions() {
+        let mirrored: String = SOLUTION
+            .lines()
+            .map(|line| line.chars().rev().collect::<String>() + "\n")
+            .collect();
+
+        let verified = verify(SOLUTION, &pentominoes()).unwrap();
+        let verified_mirror = verify(&mirrored, &pentominoes()).unwrap();
+        assert_eq!(verified.id, verified_mirror.id);
+        assert_eq!(verified.code, verified_mirror.code);
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        // the L is cut short, the I is made two cells longer by taking over two cells of the W
+        let broken = SOLUTION
+            .replacen("LLXZ", ".LXZ", 1)
+            .replacen("TWWI", "TIII", 1);
+
+        let errors = verify(&broken, &pentominoes()).unwrap_err();
+        assert!(errors.contains(&"Cell (0, 3) is not covered".to_string()));
+        assert!(errors
+            .iter()
+            .any(|e| e.starts_with("Region 'I' at (9, 0) with 7 cells")));
+        assert!(errors
+            .iter()
+            .any(|e| e.starts_with("Region 'L' at (0, 0) with 3 cells")));
+        assert!(errors.contains(&"Line(1) is not used".to_string()));
+        assert!(errors.contains(&"L(13) is not used".to_string()));
+    }
+
+    #[test]
+    fn rejects_a_block_used_twice() {
+        let inventory: Inventory = (1..=3).map(Block::Square).collect();
+        let errors = verify("AABB\nAABB\nCCDD\nCCDD\n", &inventory).unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec!["Region 'D' at (2, 2) is a Square(1), but all of those are used already"]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::ops::{Index, IndexMut};
+
+use crate::Position;
+
+pub const HOLE: usize = usize::MAX; // cell that is not part of the board
+
+/// Which edges of the board are joined, blocks can cross a joined edge and come out on the
+/// other side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topology {
+    Flat,
+    Cylinder, // left and right edges joined
+    Torus,    // left and right, and top and bottom edges joined
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct World {
+    width: usize,
+    height: usize,
+    cells: Vec<usize>, // row major, 0 means empty
+    topology: Topology,
+}
+
+impl Topology {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "flat" => Ok(Topology::Flat),
+            "cylinder" => Ok(Topology::Cylinder),
+            "torus" => Ok(Topology::Torus),
+            _ => Err(format!(
+                "Unknown topology {}, use flat, cylinder or torus",
+                name
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Topology::Flat => "flat",
+            Topology::Cylinder => "cylinder",
+            Topology::Torus => "torus",
+        }
+    }
+
+    pub fn wraps_x(&self) -> bool {
+        matches!(self, Topology::Cylinder | Topology::Torus)
+    }
+
+    pub fn wraps_y(&self) -> bool {
+        matches!(self, Topology::Torus)
+    }
+}
+
+/// Splits off the optional `topology <name>` line a board or world text may start with, flat
+/// when there is none.
+pub fn split_topology(text: &str) -> Result<(Topology, &str), String> {
+    let trimmed = text.trim_start_matches(['\n', '\r']);
+    let (first, rest) = trimmed.split_once('\n').unwrap_or((trimmed, ""));
+
+    match first.trim_end().strip_prefix("topology ") {
+        Some(name) => Ok((Topology::parse(name.trim())?, rest)),
+        None => Ok((Topology::Flat, text)),
+    }
+}
+
+/// The position `pos` ends up on in a `width` x `height` area after crossing the joined edges,
+/// None when it is outside the area.
+pub fn wrap_position(
+    topology: Topology,
+    width: usize,
+    height: usize,
+    pos: (i32, i32),
+) -> Option<Position> {
+    let wrap = |value: i32, size: usize, wraps: bool| -> Option<usize> {
+        if wraps {
+            Some(value.rem_euclid(size as i32) as usize)
+        } else if 0 <= value && value < size as i32 {
+            Some(value as usize)
+        } else {
+            None
+        }
+    };
+
+    Some((
+        wrap(pos.0, width, topology.wraps_x())?,
+        wrap(pos.1, height, topology.wraps_y())?,
+    ))
+}
+
+impl World {
+    pub fn new(width: usize, height: usize) -> Self {
+        World {
+            width,
+            height,
+            cells: vec![0; width * height],
+            topology: Topology::Flat,
+        }
+    }
+
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// See `wrap_position`.
+    pub fn wrap(&self, pos: (i32, i32)) -> Option<Position> {
+        wrap_position(self.topology, self.width, self.height, pos)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[usize]> {
+        self.cells.chunks(self.width)
+    }
+
+    /// Reads a world written by `to_text`, shorter lines are padded with holes. It may start with
+    /// a `topology` line like board files.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (topology, text) = split_topology(text)?;
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|line| line.trim_end())
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        let height = lines.len();
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        if width == 0 || height == 0 {
+            return Err("World is empty".to_string());
+        }
+
+        let mut world = World::new(width, height);
+        world.cells.fill(HOLE);
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                world[(x, y)] = char_id(c)?;
+            }
+        }
+
+        Ok(world.with_topology(topology))
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for row in self.rows() {
+            text.extend(row.iter().map(|value| id_char(*value)));
+            text.push('\n');
+        }
+
+        text
+    }
+}
+
+/// Character used for a block id in text grids.
+pub fn id_char(id: usize) -> char {
+    match id {
+        0 => '.',
+        HOLE => '#',
+        1..=26 => (b'A' + (id - 1) as u8) as char,
+        27..=52 => (b'a' + (id - 27) as u8) as char,
+        _ => '?',
+    }
+}
+
+/// Inverse of `id_char`.
+pub fn char_id(c: char) -> Result<usize, String> {
+    match c {
+        '.' => Ok(0),
+        '#' | ' ' => Ok(HOLE),
+        'A'..='Z' => Ok(c as usize - 'A' as usize + 1),
+        'a'..='z' => Ok(c as usize - 'a' as usize + 27),
+        _ => Err(format!("Unexpected character '{}'", c)),
+    }
+}
+
+impl Index<Position> for World {
+    type Output = usize;
+
+    fn index(&self, pos: Position) -> &usize {
+        &self.cells[pos.1 * self.width + pos.0]
+    }
+}
+
+impl IndexMut<Position> for World {
+    fn index_mut(&mut self, pos: Position) -> &mut usize {
+        &mut self.cells[pos.1 * self.width + pos.0]
+    }
+}